@@ -0,0 +1,39 @@
+//! Compares the time to paint a frame of a 50,000 node graph with and without culling elements that
+//! are out of view. Run with `cargo bench --bench paint`.
+
+use std::time::{Duration, Instant};
+
+use druid::{RenderContext, Size};
+use druid::piet::Device;
+
+use tree_rs::bench::PaintBench;
+
+const NODE_COUNT: usize = 50_000;
+const ROW_LENGTH: usize = 250;
+const FRAME_SIZE: Size = Size::new(1920.0, 1080.0);
+const WARM_UP_FRAMES: u32 = 2;
+const FRAMES: u32 = 20;
+
+fn main() {
+    let bench = PaintBench::grid(NODE_COUNT, ROW_LENGTH);
+    let mut device = Device::new().expect("Failed to create a render device");
+    let mut target = device.bitmap_target(FRAME_SIZE.width as usize, FRAME_SIZE.height as usize, 1.0)
+        .expect("Failed to create a bitmap to paint into");
+    for (name, cull) in [("with culling", true), ("without culling", false)] {
+        let mut ctx = target.render_context();
+        let mut paint_frame = || {
+            bench.paint(&mut ctx, FRAME_SIZE, cull);
+            ctx.status().expect("Painting failed");
+        };
+        for _ in 0..WARM_UP_FRAMES {
+            paint_frame();
+        }
+        let start_time = Instant::now();
+        for _ in 0..FRAMES {
+            paint_frame();
+        }
+        let frame_time: Duration = start_time.elapsed() / FRAMES;
+        println!("Paint {}: {:.3}ms per frame", name, frame_time.as_secs_f64() * 1000.0);
+        ctx.finish().expect("Painting failed");
+    }
+}
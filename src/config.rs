@@ -0,0 +1,127 @@
+use std::{env, fs, io};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use druid::{Env, Event, EventCtx, TimerToken, Widget};
+use druid::widget::Controller;
+use serde::Deserialize;
+
+use crate::graph_view::SHOW_ERROR;
+use crate::theme::Theme;
+
+/// How often the config file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings read from `tree-rs/config.json` in the user's config directory
+#[derive(Default, Debug, PartialEq, Deserialize)]
+pub(crate) struct Config {
+    /// Theme to use, which is switched to whenever it changes in the file while the app is running
+    #[serde(default)]
+    pub(crate) theme: Option<Theme>,
+}
+
+/// Where the config file is, if there's a config directory to look in
+pub(crate) fn path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("tree-rs").join("config.json"))
+}
+
+#[derive(Debug)]
+pub(crate) enum ConfigError {
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "I/O error: {}", e),
+            ConfigError::Format(e) => write!(f, "invalid config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The config file's settings, or `None` if there isn't one
+pub(crate) fn load(path: &Path) -> Result<Option<Config>, ConfigError> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(ConfigError::Io(e)),
+    };
+    serde_json::from_str(&json).map(Some).map_err(ConfigError::Format)
+}
+
+/// Message shown in the view when the config file can't be loaded
+pub(crate) fn error_message(path: &Path, error: &ConfigError) -> String {
+    format!("Failed to load config {}: {}", path.display(), error)
+}
+
+/// Switches to the theme set in the config file whenever the file changes. Config files that can't
+/// be loaded leave the theme as it is and are reported through the view's error banner.
+pub(crate) struct ConfigFollower {
+    path: Option<PathBuf>,
+    last_modified: Option<SystemTime>,
+    timer: TimerToken,
+    /// Why the config couldn't be loaded at startup, reported once the window is open
+    startup_error: Option<String>,
+}
+
+impl ConfigFollower {
+    pub(crate) fn new(path: Option<PathBuf>, startup_error: Option<String>) -> Self {
+        // The file has already been read at startup, so only changes from here on are followed
+        let last_modified = path.as_deref().and_then(modified_time);
+        ConfigFollower { path, last_modified, timer: TimerToken::INVALID, startup_error }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl<W: Widget<Theme>> Controller<Theme, W> for ConfigFollower {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut Theme, env: &Env) {
+        let Some(path) = &self.path else {
+            return child.event(ctx, event, data, env);
+        };
+        match event {
+            Event::WindowConnected => {
+                self.timer = ctx.request_timer(POLL_INTERVAL);
+                if let Some(message) = self.startup_error.take() {
+                    ctx.submit_command(SHOW_ERROR.with(message));
+                }
+            }
+            Event::Timer(token) if *token == self.timer => {
+                let modified = modified_time(path);
+                if modified != self.last_modified {
+                    self.last_modified = modified;
+                    match load(path) {
+                        Ok(Some(Config { theme: Some(theme) })) => *data = theme,
+                        Ok(_) => {}
+                        Err(e) => ctx.submit_command(SHOW_ERROR.with(error_message(path, &e))),
+                    }
+                }
+                self.timer = ctx.request_timer(POLL_INTERVAL);
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_are_read_by_name() {
+        let config: Config = serde_json::from_str(r#"{"theme": "high-contrast"}"#).unwrap();
+        assert_eq!(config.theme, Some(Theme::HighContrast));
+        assert_eq!(serde_json::from_str::<Config>("{}").unwrap(), Config::default());
+        assert!(serde_json::from_str::<Config>(r#"{"theme": "sepia"}"#).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graph::Graph;
+
+/// Schema version written into every saved document.
+/// Fields added to the model should be `#[serde(default)]` so that older documents keep loading;
+/// bump this whenever the format changes so files from newer versions of the app are rejected
+/// rather than silently losing data.
+///
+/// Version history:
+/// 1. Nodes with text and rects, and edges between them
+/// 2. Edges have an `edge_type`, defaulting to undirected
+/// 3. Nodes have an `auto_size` policy, defaulting to a fixed size
+/// 4. Nodes have a `shape`, defaulting to a rectangle
+/// 5. Nodes and edges have a `style`, with every property unset by default
+/// 6. Style colours can name a theme colour, like `theme:red`, as well as being `#rrggbbaa` hex
+pub(crate) const CURRENT_VERSION: u32 = 6;
+
+pub(crate) const FILE_EXTENSION: &str = "tree";
+
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    graph: &'a Graph,
+}
+
+#[derive(Deserialize)]
+struct DocumentHeader {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    graph: Graph,
+}
+
+#[derive(Debug)]
+pub(crate) enum DocumentError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+impl Display for DocumentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentError::Io(e) => write!(f, "I/O error: {}", e),
+            DocumentError::Format(e) => write!(f, "invalid document: {}", e),
+            DocumentError::UnsupportedVersion(v) =>
+                write!(f, "document version {} is not supported (latest supported is {})", v, CURRENT_VERSION),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+impl From<io::Error> for DocumentError {
+    fn from(e: io::Error) -> Self {
+        DocumentError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DocumentError {
+    fn from(e: serde_json::Error) -> Self {
+        DocumentError::Format(e)
+    }
+}
+
+pub(crate) fn to_json(graph: &Graph) -> Result<String, DocumentError> {
+    Ok(serde_json::to_string_pretty(&DocumentRef { version: CURRENT_VERSION, graph })?)
+}
+
+pub(crate) fn from_json(json: &str) -> Result<Graph, DocumentError> {
+    // Read the version on its own first so a newer file reports a version error, not a parse error
+    let header: DocumentHeader = serde_json::from_str(json)?;
+    if header.version == 0 || header.version > CURRENT_VERSION {
+        return Err(DocumentError::UnsupportedVersion(header.version));
+    }
+    let document: Document = serde_json::from_str(json)?;
+    Ok(document.graph)
+}
+
+pub(crate) fn save(graph: &Graph, path: &Path) -> Result<(), DocumentError> {
+    fs::write(path, to_json(graph)?)?;
+    Ok(())
+}
+
+pub(crate) fn load(path: &Path) -> Result<Graph, DocumentError> {
+    from_json(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::kurbo::{Point, Rect};
+
+    use crate::graph::edge::{Edge, EdgeType};
+    use crate::graph::node::{AutoSize, Node, NodeId, NodeShape};
+    use crate::graph::style::{LineStyle, NodeStyle};
+
+    use super::*;
+
+    #[test]
+    fn graph_round_trips_through_json() {
+        let from = Node::new(Point::new(-10.0, 20.5), None);
+        let mut to = Node::new(Point::new(300.0, 400.0), None);
+        to.text = String::from("to");
+        to.rect = Rect::new(250.0, 350.0, 380.25, 470.0);
+        let graph = Graph {
+            edges: vec![Edge::new(from.id, to.id, EdgeType::Bidirectional)],
+            nodes: vec![from, to],
+        };
+        assert_eq!(from_json(&to_json(&graph).unwrap()).unwrap(), graph);
+    }
+
+    #[test]
+    fn version_1_documents_load_with_default_edge_types_and_node_settings() {
+        let json = r#"{
+            "version": 1,
+            "graph": {
+                "nodes": [
+                    {"id": "6c3a4bb8-0f9e-4d7a-9b1e-2a1f2f1d8e01", "text": "a", "rect": {"x0": 0.0, "y0": 0.0, "x1": 100.0, "y1": 60.0}},
+                    {"id": "6c3a4bb8-0f9e-4d7a-9b1e-2a1f2f1d8e02", "text": "b", "rect": {"x0": 200.0, "y0": 0.0, "x1": 300.0, "y1": 60.0}}
+                ],
+                "edges": [
+                    {"id": "6c3a4bb8-0f9e-4d7a-9b1e-2a1f2f1d8e03",
+                     "from_node_id": "6c3a4bb8-0f9e-4d7a-9b1e-2a1f2f1d8e01",
+                     "to_node_id": "6c3a4bb8-0f9e-4d7a-9b1e-2a1f2f1d8e02"}
+                ]
+            }
+        }"#;
+        let graph = from_json(json).unwrap();
+        assert_eq!(graph.nodes[1].text, "b");
+        assert_eq!(graph.edges[0].edge_type, EdgeType::Undirected);
+        assert_eq!(graph.nodes[0].auto_size, AutoSize::Fixed);
+        assert_eq!(graph.nodes[0].shape, NodeShape::Rectangle);
+        assert_eq!(graph.nodes[0].style, NodeStyle::default());
+        assert_eq!(graph.edges[0].style, LineStyle::default());
+    }
+
+    #[test]
+    fn newer_documents_are_rejected() {
+        let json = format!(r#"{{"version": {}, "graph": {{"nodes": [], "edges": []}}}}"#, CURRENT_VERSION + 1);
+        assert!(matches!(from_json(&json), Err(DocumentError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn node_ids_are_stored_as_uuid_strings() {
+        let node = Node::new(Point::ORIGIN, None);
+        let json = to_json(&Graph { nodes: vec![node.clone()], edges: vec![] }).unwrap();
+        assert!(json.contains(&format!("\"{}\"", node.id.0)));
+        let NodeId(loaded_id) = from_json(&json).unwrap().nodes[0].id;
+        assert_eq!(loaded_id, node.id.0);
+    }
+}
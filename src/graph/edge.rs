@@ -1,12 +1,53 @@
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::graph::node::NodeId;
+use crate::graph::style::LineStyle;
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub(crate) struct EdgeId(pub Uuid);
 
-#[derive(Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum EdgeType {
+    /// A plain line with no arrowheads
+    #[default]
+    Undirected,
+    /// An arrow pointing from the `from` node to the `to` node
+    Directional,
+    /// Arrowheads at both ends
+    Bidirectional,
+}
+
+impl EdgeType {
+    /// The type after this one when cycling through edge types in the UI
+    pub(crate) fn next(self) -> Self {
+        match self {
+            EdgeType::Undirected => EdgeType::Directional,
+            EdgeType::Directional => EdgeType::Bidirectional,
+            EdgeType::Bidirectional => EdgeType::Undirected,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Edge {
     pub(crate) id: EdgeId,
     pub(crate) from_node_id: NodeId,
     pub(crate) to_node_id: NodeId,
+    #[serde(default)]
+    pub(crate) edge_type: EdgeType,
+    #[serde(default)]
+    pub(crate) style: LineStyle,
+}
+
+impl Edge {
+    pub(crate) fn new(from_node_id: NodeId, to_node_id: NodeId, edge_type: EdgeType) -> Self {
+        Edge {
+            id: EdgeId(Uuid::new_v4()),
+            from_node_id,
+            to_node_id,
+            edge_type,
+            style: LineStyle::default(),
+        }
+    }
 }
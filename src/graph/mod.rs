@@ -1,10 +1,14 @@
+use serde::{Deserialize, Serialize};
+
 use crate::graph::edge::Edge;
 use crate::graph::node::Node;
 
+pub mod document;
 pub mod edge;
 pub mod node;
+pub mod style;
 
-#[derive(Default)]
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Graph {
     pub(crate) nodes: Vec<Node>,
     pub(crate) edges: Vec<Edge>,
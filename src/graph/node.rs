@@ -1,13 +1,80 @@
 use druid::kurbo::{Point, Rect, Size};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+use crate::graph::style::NodeStyle;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub(crate) struct NodeId(pub Uuid);
 
+/// How a node's size follows its text as it changes
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum AutoSize {
+    /// The node keeps its size and text wraps to its width
+    #[default]
+    Fixed,
+    /// The node keeps its width and its height fits the wrapped text
+    GrowHeight,
+    /// The node is as wide as its text, wrapping at `max_width`, and as tall as the wrapped text
+    FitBoth { max_width: f64 },
+}
+
+impl AutoSize {
+    /// Widest a node fitting both dimensions gets before its text wraps, in scene units
+    pub(crate) const DEFAULT_MAX_WIDTH: f64 = 240.0;
+
+    /// The policy after this one when cycling through policies in the UI
+    pub(crate) fn next(self) -> Self {
+        match self {
+            AutoSize::Fixed => AutoSize::GrowHeight,
+            AutoSize::GrowHeight => AutoSize::FitBoth { max_width: AutoSize::DEFAULT_MAX_WIDTH },
+            AutoSize::FitBoth { .. } => AutoSize::Fixed,
+        }
+    }
+}
+
+/// Outline a node is drawn with, which also decides where it can be clicked and where its edges end.
+/// Every shape is stretched to fill the node's rect.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum NodeShape {
+    #[default]
+    Rectangle,
+    RoundedRect,
+    Ellipse,
+    Diamond,
+    /// A rect with fully rounded ends
+    Pill,
+    Hexagon,
+    Cloud,
+}
+
+impl NodeShape {
+    /// The shape after this one when cycling through shapes in the UI
+    pub(crate) fn next(self) -> Self {
+        match self {
+            NodeShape::Rectangle => NodeShape::RoundedRect,
+            NodeShape::RoundedRect => NodeShape::Ellipse,
+            NodeShape::Ellipse => NodeShape::Diamond,
+            NodeShape::Diamond => NodeShape::Pill,
+            NodeShape::Pill => NodeShape::Hexagon,
+            NodeShape::Hexagon => NodeShape::Cloud,
+            NodeShape::Cloud => NodeShape::Rectangle,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Node {
     pub(crate) id: NodeId,
     pub(crate) text: String,
     pub(crate) rect: Rect,
+    #[serde(default)]
+    pub(crate) auto_size: AutoSize,
+    #[serde(default)]
+    pub(crate) shape: NodeShape,
+    #[serde(default)]
+    pub(crate) style: NodeStyle,
 }
 
 impl Node {
@@ -18,6 +85,9 @@ impl Node {
             id: NodeId(Uuid::new_v4()),
             text: String::new(),
             rect: Rect::from_center_size(center, size.unwrap_or(Node::DEFAULT_SIZE)),
+            auto_size: AutoSize::default(),
+            shape: NodeShape::default(),
+            style: NodeStyle::default(),
         }
     }
 }
@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use druid::Color;
+use serde::{Deserialize, Serialize};
+
+/// A colour with alpha, written `#rrggbbaa`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) struct Rgba(pub u32);
+
+impl Display for Rgba {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:08x}", self.0)
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = String;
+
+    /// Parses `#rrggbbaa`, or `#rrggbb` for an opaque colour
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#')
+            .filter(|hex| (hex.len() == 6 || hex.len() == 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| format!("expected a colour like #rrggbb or #rrggbbaa, got {:?}", s))?;
+        let value = u32::from_str_radix(hex, 16).unwrap();
+        Ok(Rgba(if hex.len() == 6 { value << 8 | 0xff } else { value }))
+    }
+}
+
+impl From<Rgba> for Color {
+    fn from(rgba: Rgba) -> Self {
+        Color::from_rgba32_u32(rgba.0)
+    }
+}
+
+/// A colour that every theme has its own shade of, so that styles using it suit whichever theme is in use
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum ThemeColor {
+    Foreground,
+    Background,
+    Muted,
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    /// Soft versions of the colours above, for filling nodes that hold text
+    MutedTint,
+    RedTint,
+    YellowTint,
+    GreenTint,
+    BlueTint,
+}
+
+impl ThemeColor {
+    pub(crate) const ALL: [ThemeColor; 12] = [
+        ThemeColor::Foreground, ThemeColor::Background, ThemeColor::Muted, ThemeColor::Red, ThemeColor::Yellow,
+        ThemeColor::Green, ThemeColor::Blue, ThemeColor::MutedTint, ThemeColor::RedTint, ThemeColor::YellowTint,
+        ThemeColor::GreenTint, ThemeColor::BlueTint,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ThemeColor::Foreground => "foreground",
+            ThemeColor::Background => "background",
+            ThemeColor::Muted => "muted",
+            ThemeColor::Red => "red",
+            ThemeColor::Yellow => "yellow",
+            ThemeColor::Green => "green",
+            ThemeColor::Blue => "blue",
+            ThemeColor::MutedTint => "muted-tint",
+            ThemeColor::RedTint => "red-tint",
+            ThemeColor::YellowTint => "yellow-tint",
+            ThemeColor::GreenTint => "green-tint",
+            ThemeColor::BlueTint => "blue-tint",
+        }
+    }
+}
+
+/// A colour in a style, stored in documents as `#rrggbbaa`, or as `theme:<name>` to use a theme colour
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub(crate) enum StyleColor {
+    Rgba(Rgba),
+    Theme(ThemeColor),
+}
+
+const THEME_COLOR_PREFIX: &str = "theme:";
+
+impl From<StyleColor> for String {
+    fn from(color: StyleColor) -> Self {
+        match color {
+            StyleColor::Rgba(rgba) => rgba.to_string(),
+            StyleColor::Theme(theme_color) => format!("{}{}", THEME_COLOR_PREFIX, theme_color.name()),
+        }
+    }
+}
+
+impl TryFrom<String> for StyleColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.strip_prefix(THEME_COLOR_PREFIX) {
+            Some(name) => ThemeColor::ALL.into_iter().find(|theme_color| theme_color.name() == name)
+                .map(StyleColor::Theme)
+                .ok_or_else(|| format!("unknown theme colour {:?}", name)),
+            None => s.parse().map(StyleColor::Rgba),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) enum TextAlign {
+    Start,
+    Center,
+    End,
+}
+
+/// How a line is drawn, whether it's an edge or a node's border.
+/// Each property that's unset is drawn the default way.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LineStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stroke: Option<StyleColor>,
+    /// In scene units
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) stroke_width: Option<f64>,
+    /// Alternating lengths of dashes and gaps in scene units, or a solid line when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dash: Option<Vec<f64>>,
+}
+
+/// How a node is drawn. Each property that's unset is drawn the default way.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NodeStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fill: Option<StyleColor>,
+    /// The node's border
+    #[serde(flatten)]
+    pub(crate) line: LineStyle,
+    /// A font name, or one of the generic families `serif`, `sans-serif`, `monospace` and `system-ui`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) font_family: Option<String>,
+    /// In scene units
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) font_size: Option<f64>,
+    /// From 1 to 1000, where 400 is regular and 700 is bold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) font_weight: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) text_alignment: Option<TextAlign>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_are_stored_as_hex_strings_or_theme_names() {
+        assert_eq!(serde_json::to_string(&StyleColor::Rgba(Rgba(0x1a2b3cff))).unwrap(), "\"#1a2b3cff\"");
+        assert_eq!(serde_json::from_str::<StyleColor>("\"#1A2B3C\"").unwrap(), StyleColor::Rgba(Rgba(0x1a2b3cff)));
+        assert!(serde_json::from_str::<StyleColor>("\"1a2b3c\"").is_err());
+        assert!(serde_json::from_str::<StyleColor>("\"#12345\"").is_err());
+        for theme_color in ThemeColor::ALL {
+            let json = serde_json::to_string(&StyleColor::Theme(theme_color)).unwrap();
+            assert_eq!(serde_json::from_str::<StyleColor>(&json).unwrap(), StyleColor::Theme(theme_color));
+        }
+        assert_eq!(serde_json::to_string(&StyleColor::Theme(ThemeColor::RedTint)).unwrap(), "\"theme:red-tint\"");
+        assert!(serde_json::from_str::<StyleColor>("\"theme:purple\"").is_err());
+    }
+
+    #[test]
+    fn only_set_properties_are_stored() {
+        let style = NodeStyle {
+            fill: Some(StyleColor::Rgba(Rgba(0xffffffff))),
+            line: LineStyle { stroke_width: Some(3.0), ..LineStyle::default() },
+            ..NodeStyle::default()
+        };
+        let json = serde_json::to_string(&style).unwrap();
+        assert_eq!(json, r##"{"fill":"#ffffffff","stroke_width":3.0}"##);
+        assert_eq!(serde_json::from_str::<NodeStyle>(&json).unwrap(), style);
+        assert_eq!(serde_json::from_str::<NodeStyle>("{}").unwrap(), NodeStyle::default());
+    }
+}
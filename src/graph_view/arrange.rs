@@ -0,0 +1,128 @@
+use druid::{Rect, Size, Vec2};
+
+use crate::graph::node::NodeId;
+
+/// Which edge or centre line of the selection's bounds nodes are lined up on
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Alignment {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Centres on one vertical line
+    HorizontalCenter,
+    /// Centres on one horizontal line
+    VerticalCenter,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+fn bounds(nodes: &[(NodeId, Rect)]) -> Rect {
+    nodes.iter().skip(1).fold(nodes[0].1, |bounds, (_, rect)| bounds.union(*rect))
+}
+
+/// Moves every node onto the same edge or centre line of the bounds of them all
+pub(crate) fn align(nodes: &[(NodeId, Rect)], alignment: Alignment) -> Vec<(NodeId, Rect)> {
+    if nodes.is_empty() {
+        return vec![];
+    }
+    let bounds = bounds(nodes);
+    nodes.iter().map(|(node_id, rect)| {
+        let offset = match alignment {
+            Alignment::Left => Vec2::new(bounds.x0 - rect.x0, 0.0),
+            Alignment::Right => Vec2::new(bounds.x1 - rect.x1, 0.0),
+            Alignment::Top => Vec2::new(0.0, bounds.y0 - rect.y0),
+            Alignment::Bottom => Vec2::new(0.0, bounds.y1 - rect.y1),
+            Alignment::HorizontalCenter => Vec2::new(bounds.center().x - rect.center().x, 0.0),
+            Alignment::VerticalCenter => Vec2::new(0.0, bounds.center().y - rect.center().y),
+        };
+        (*node_id, *rect + offset)
+    }).collect()
+}
+
+/// Spaces nodes out so the gaps between them along an axis are equal, keeping the first and last in
+/// place. Nodes keep their order along the axis, taken from their centres.
+pub(crate) fn distribute(nodes: &[(NodeId, Rect)], axis: Axis) -> Vec<(NodeId, Rect)> {
+    if nodes.len() < 3 {
+        return nodes.to_vec();
+    }
+    type Measure = fn(&Rect) -> f64;
+    let (start, end, length): (Measure, Measure, Measure) = match axis {
+        Axis::Horizontal => (|r: &Rect| r.x0, |r: &Rect| r.x1, |r: &Rect| r.width()),
+        Axis::Vertical => (|r: &Rect| r.y0, |r: &Rect| r.y1, |r: &Rect| r.height()),
+    };
+    let mut sorted = nodes.to_vec();
+    sorted.sort_by(|(_, a), (_, b)| (start(a) + end(a)).total_cmp(&(start(b) + end(b))));
+    let first = start(&sorted[0].1);
+    let last = end(&sorted[sorted.len() - 1].1);
+    let total_length: f64 = sorted.iter().map(|(_, r)| length(r)).sum();
+    let gap = (last - first - total_length) / (sorted.len() - 1) as f64;
+    let mut cursor = first;
+    sorted.into_iter().map(|(node_id, rect)| {
+        let moved = match axis {
+            Axis::Horizontal => rect.with_origin((cursor, rect.y0)),
+            Axis::Vertical => rect.with_origin((rect.x0, cursor)),
+        };
+        cursor += length(&rect) + gap;
+        (node_id, moved)
+    }).collect()
+}
+
+/// Resizes every node to the size of the largest one, keeping their centres where they are
+pub(crate) fn make_same_size(nodes: &[(NodeId, Rect)]) -> Vec<(NodeId, Rect)> {
+    let size = nodes.iter()
+        .map(|(_, rect)| rect.size())
+        .max_by(|a, b| a.area().total_cmp(&b.area()))
+        .unwrap_or(Size::ZERO);
+    nodes.iter().map(|(node_id, rect)| (*node_id, Rect::from_center_size(rect.center(), size))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn nodes(rects: &[Rect]) -> Vec<(NodeId, Rect)> {
+        rects.iter().map(|r| (NodeId(Uuid::new_v4()), *r)).collect()
+    }
+
+    fn rects(nodes: &[(NodeId, Rect)]) -> Vec<Rect> {
+        nodes.iter().map(|(_, r)| *r).collect()
+    }
+
+    #[test]
+    fn nodes_align_to_the_edges_and_centre_of_their_bounds() {
+        let nodes = nodes(&[Rect::new(0.0, 0.0, 100.0, 60.0), Rect::new(150.0, 100.0, 200.0, 300.0)]);
+        assert_eq!(rects(&align(&nodes, Alignment::Left)), vec![nodes[0].1, Rect::new(0.0, 100.0, 50.0, 300.0)]);
+        assert_eq!(rects(&align(&nodes, Alignment::Bottom)), vec![Rect::new(0.0, 240.0, 100.0, 300.0), nodes[1].1]);
+        assert_eq!(rects(&align(&nodes, Alignment::HorizontalCenter)),
+                   vec![Rect::new(50.0, 0.0, 150.0, 60.0), Rect::new(75.0, 100.0, 125.0, 300.0)]);
+        assert_eq!(rects(&align(&nodes, Alignment::VerticalCenter)),
+                   vec![Rect::new(0.0, 120.0, 100.0, 180.0), Rect::new(150.0, 50.0, 200.0, 250.0)]);
+    }
+
+    #[test]
+    fn distributing_leaves_equal_gaps_between_nodes() {
+        let nodes = nodes(&[
+            Rect::new(400.0, 0.0, 500.0, 60.0),
+            Rect::new(0.0, 0.0, 100.0, 60.0),
+            Rect::new(110.0, 50.0, 150.0, 80.0),
+        ]);
+        let distributed = distribute(&nodes, Axis::Horizontal);
+        // Sorted by position, with the 260 units not covered by nodes split into two gaps
+        assert_eq!(distributed.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![nodes[1].0, nodes[2].0, nodes[0].0]);
+        assert_eq!(rects(&distributed)[1], Rect::new(230.0, 50.0, 270.0, 80.0));
+        assert_eq!(rects(&distributed)[2], nodes[0].1);
+    }
+
+    #[test]
+    fn nodes_are_made_the_size_of_the_largest() {
+        let nodes = nodes(&[Rect::new(0.0, 0.0, 100.0, 60.0), Rect::new(200.0, 0.0, 240.0, 200.0)]);
+        assert_eq!(rects(&make_same_size(&nodes)), vec![Rect::new(30.0, -70.0, 70.0, 130.0), nodes[1].1]);
+    }
+}
@@ -0,0 +1,90 @@
+use druid::{Rect, Size};
+use druid::piet::{PietText, TextLayout, TextLayoutBuilder};
+
+use crate::graph::node::AutoSize;
+use crate::graph::style::NodeStyle;
+use crate::graph_view::NODE_TEXT_PADDING;
+use crate::graph_view::resize::{anchored_resize, Handle, MIN_NODE_SIZE};
+use crate::graph_view::style::node_text_layout;
+
+/// Rect a node with the given text and policy should have. While `rect` is being resized by dragging
+/// `handle`, the sides opposite the handle stay where they are, otherwise the fitted rect is centred on it.
+pub(crate) fn fit(text_factory: &mut PietText, text: &str, style: &NodeStyle, auto_size: AutoSize, rect: Rect,
+                  handle: Option<Handle>) -> Rect {
+    if auto_size == AutoSize::Fixed {
+        return rect;
+    }
+    let text_layout = node_text_layout(text_factory, text, style, 1.0, wrap_width(auto_size, rect)).build().unwrap();
+    fitted_rect(auto_size, rect, text_layout.size(), handle)
+}
+
+/// Width text is wrapped at in a node, in scene units
+fn wrap_width(auto_size: AutoSize, rect: Rect) -> f64 {
+    let width = match auto_size {
+        AutoSize::FitBoth { max_width } => max_width,
+        AutoSize::Fixed | AutoSize::GrowHeight => rect.width(),
+    };
+    width - 2.0 * NODE_TEXT_PADDING
+}
+
+/// Rect that fits text laid out at `text_size`, placed as described for `fit` and never smaller than
+/// `MIN_NODE_SIZE`. A node that grows its height can be resized taller than its text, but not shorter.
+fn fitted_rect(auto_size: AutoSize, rect: Rect, text_size: Size, handle: Option<Handle>) -> Rect {
+    let height = (text_size.height + 2.0 * NODE_TEXT_PADDING).max(MIN_NODE_SIZE.height);
+    let size = match auto_size {
+        AutoSize::Fixed => return rect,
+        AutoSize::GrowHeight if handle.is_some() => Size::new(rect.width(), height.max(rect.height())),
+        AutoSize::GrowHeight => Size::new(rect.width(), height),
+        // Rounded up so the text doesn't wrap again when it's laid out in the fitted node
+        AutoSize::FitBoth { max_width } => Size::new(
+            (text_size.width.ceil() + 2.0 * NODE_TEXT_PADDING).clamp(MIN_NODE_SIZE.width, max_width.max(MIN_NODE_SIZE.width)),
+            height,
+        ),
+    };
+    match handle {
+        Some(handle) => anchored_resize(rect, handle, size),
+        None => Rect::from_center_size(rect.center(), size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Vec2;
+
+    use crate::graph_view::resize::resize;
+
+    use super::*;
+
+    const RECT: Rect = Rect { x0: 0.0, y0: 0.0, x1: 100.0, y1: 60.0 };
+
+    #[test]
+    fn grow_height_keeps_width_and_center() {
+        let fitted = fitted_rect(AutoSize::GrowHeight, RECT, Size::new(90.0, 112.0), None);
+        assert_eq!(fitted, Rect::new(0.0, -30.0, 100.0, 90.0));
+        assert_eq!(fitted_rect(AutoSize::Fixed, RECT, Size::new(90.0, 112.0), None), RECT);
+        assert_eq!(wrap_width(AutoSize::GrowHeight, RECT), 100.0 - 2.0 * NODE_TEXT_PADDING);
+    }
+
+    #[test]
+    fn fit_both_stays_between_the_minimum_and_max_width() {
+        let fit_both = AutoSize::FitBoth { max_width: 200.0 };
+        let short = fitted_rect(fit_both, RECT, Size::new(10.2, 28.0), None);
+        assert_eq!(short.size(), Size::new(MIN_NODE_SIZE.width, 28.0 + 2.0 * NODE_TEXT_PADDING));
+        assert_eq!(short.center(), RECT.center());
+        let long = fitted_rect(fit_both, RECT, Size::new(191.5, 56.0), None);
+        assert_eq!(long.width(), 200.0);
+        assert_eq!(wrap_width(fit_both, RECT), 200.0 - 2.0 * NODE_TEXT_PADDING);
+    }
+
+    #[test]
+    fn resizing_keeps_the_side_opposite_the_handle() {
+        let text_size = Size::new(90.0, 40.0);
+        let dragged = resize(RECT, Handle::Bottom, Vec2::new(0.0, 50.0), false);
+        assert_eq!(fitted_rect(AutoSize::GrowHeight, dragged, text_size, Some(Handle::Bottom)), dragged);
+        let dragged = resize(RECT, Handle::Bottom, Vec2::new(0.0, -30.0), false);
+        assert_eq!(fitted_rect(AutoSize::GrowHeight, dragged, text_size, Some(Handle::Bottom)),
+                   Rect::new(0.0, 0.0, 100.0, 40.0 + 2.0 * NODE_TEXT_PADDING));
+        let fitted = fitted_rect(AutoSize::FitBoth { max_width: 200.0 }, RECT, text_size, Some(Handle::TopLeft));
+        assert_eq!((fitted.x1, fitted.y1), (RECT.x1, RECT.y1));
+    }
+}
@@ -0,0 +1,43 @@
+//! Entry points for the benchmarks in `benches/`, which can only use the crate's public API
+
+use druid::{Env, Point, Size};
+use druid::piet::Piet;
+
+use crate::graph::edge::{Edge, EdgeType};
+use crate::graph::Graph;
+use crate::graph::node::Node;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::GraphView;
+use crate::theme::Theme;
+
+/// A view of a large graph, along with the environment it's painted with
+pub struct PaintBench {
+    view: GraphView,
+    env: Env,
+}
+
+impl PaintBench {
+    /// A view of `node_count` nodes in rows of `row_length`, each joined to the next one in its row
+    pub fn grid(node_count: usize, row_length: usize) -> Self {
+        let nodes: Vec<Node> = (0..node_count)
+            .map(|i| {
+                let center = Point::new((i % row_length) as f64 * 200.0, (i / row_length) as f64 * 120.0);
+                Node { text: format!("Node {}", i), ..Node::new(center, None) }
+            })
+            .collect();
+        let edges = (1..node_count)
+            .filter(|i| i % row_length != 0)
+            .map(|i| Edge::new(nodes[i - 1].id, nodes[i].id, EdgeType::Directional))
+            .collect();
+        let mut view = GraphView::new();
+        view.set_display_graph(DisplayGraph::from(&Graph { nodes, edges }), None);
+        let mut env = Env::default();
+        Theme::default().configure_env(&mut env);
+        PaintBench { view, env }
+    }
+
+    /// Paints a frame of the given size, which paints every element in the graph unless `cull` is set
+    pub fn paint(&self, ctx: &mut Piet, size: Size, cull: bool) {
+        self.view.paint_scene(ctx, size, &self.env, cull);
+    }
+}
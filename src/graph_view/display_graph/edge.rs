@@ -1,12 +1,18 @@
-use druid::Point;
+use druid::{Point, Rect};
 
-use crate::graph::edge::{Edge, EdgeId};
+use crate::graph::edge::{Edge, EdgeId, EdgeType};
 use crate::graph::node::NodeId;
+use crate::graph::style::LineStyle;
+use crate::graph_view::display_graph::node::DisplayNode;
+use crate::graph_view::display_graph::shape::clip_to_border;
 
+#[derive(Clone)]
 pub(crate) struct DisplayEdge {
     pub(crate) id: EdgeId,
     pub(crate) from_node: NodeId,
     pub(crate) to_node: NodeId,
+    pub(crate) edge_type: EdgeType,
+    pub(crate) style: LineStyle,
     pub(crate) start_point: Point,
     pub(crate) end_point: Point,
 }
@@ -16,8 +22,53 @@ impl DisplayEdge {
             id: edge.id,
             from_node: edge.from_node_id,
             to_node: edge.to_node_id,
+            edge_type: edge.edge_type,
+            style: edge.style.clone(),
             start_point,
             end_point
         }
     }
-}
\ No newline at end of file
+
+    /// Whether any part of the edge's line lies within `rect`
+    pub(crate) fn intersects_rect(&self, rect: Rect) -> bool {
+        // Liang-Barsky: clip the parametric line start + t * delta (0 <= t <= 1) against each side
+        let delta = self.end_point - self.start_point;
+        let (mut t_min, mut t_max) = (0.0f64, 1.0f64);
+        for (p, q) in [
+            (-delta.x, self.start_point.x - rect.x0),
+            (delta.x, rect.x1 - self.start_point.x),
+            (-delta.y, self.start_point.y - rect.y0),
+            (delta.y, rect.y1 - self.start_point.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return false;
+                }
+            } else if p < 0.0 {
+                t_min = t_min.max(q / p);
+            } else {
+                t_max = t_max.min(q / p);
+            }
+        }
+        t_min <= t_max
+    }
+}
+
+impl From<&DisplayEdge> for Edge {
+    fn from(edge: &DisplayEdge) -> Self {
+        Edge {
+            id: edge.id,
+            from_node_id: edge.from_node,
+            to_node_id: edge.to_node,
+            edge_type: edge.edge_type,
+            style: edge.style.clone(),
+        }
+    }
+}
+
+/// Start and end points for an edge between two nodes, where the line between the node centres
+/// crosses each node's outline
+pub(crate) fn clipped_endpoints(from_node: &DisplayNode, to_node: &DisplayNode) -> (Point, Point) {
+    (clip_to_border(from_node.shape, from_node.rect, to_node.rect.center()),
+     clip_to_border(to_node.shape, to_node.rect, from_node.rect.center()))
+}
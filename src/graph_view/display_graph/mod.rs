@@ -1,24 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use druid::{Point, Vec2};
+use druid::{Point, Rect, Vec2};
+use indexmap::IndexMap;
 use rstar::{AABB, PointDistance, RTree, RTreeObject};
-use rstar::primitives::Rectangle;
+use rstar::primitives::{Line, Rectangle};
 
 use crate::graph::edge::{Edge, EdgeId};
 use crate::graph::Graph;
-use crate::graph::node::{Node, NodeId};
+use crate::graph::node::{Node, NodeId, NodeShape};
 use crate::graph_view::display_graph::edge::DisplayEdge;
 use crate::graph_view::display_graph::node::DisplayNode;
 use crate::graph_view::element_id::ElementId;
 
 pub(crate) mod node;
 pub(crate) mod edge;
+pub(crate) mod shape;
 
 type RPoint = (f64, f64);
 
+/// Exact shape of an indexed element, used for hit-testing within its bounding box
+#[derive(Copy, Clone)]
+enum Geometry {
+    /// A node's outline, filling the bounding box
+    Node(NodeShape),
+    Segment(RPoint, RPoint),
+}
+
 struct RegionRef {
     id: ElementId,
     region: AABB<RPoint>,
+    geometry: Geometry,
 }
 
 impl PartialEq<Self> for RegionRef {
@@ -32,6 +43,7 @@ impl From<&DisplayNode> for RegionRef {
         RegionRef {
             id: ElementId::Node(n.id),
             region: AABB::from_corners((n.rect.x0, n.rect.y0), (n.rect.x1, n.rect.y1)),
+            geometry: Geometry::Node(n.shape),
         }
     }
 }
@@ -41,6 +53,7 @@ impl From<&DisplayEdge> for RegionRef {
         RegionRef {
             id: ElementId::Edge(e.id),
             region: AABB::from_corners(RPoint::from(e.start_point), RPoint::from(e.end_point)),
+            geometry: Geometry::Segment(RPoint::from(e.start_point), RPoint::from(e.end_point)),
         }
     }
 }
@@ -53,107 +66,291 @@ impl RTreeObject for RegionRef {
     }
 }
 
+impl RegionRef {
+    fn rect(&self) -> Rect {
+        Rect::from_points(self.region.lower(), self.region.upper())
+    }
+}
+
 impl PointDistance for RegionRef {
     fn distance_2(&self, point: &RPoint) -> f64 {
-        Rectangle::from(self.envelope()).distance_2(point)
+        match self.geometry {
+            Geometry::Node(NodeShape::Rectangle) => Rectangle::from(self.envelope()).distance_2(point),
+            Geometry::Node(node_shape) => shape::distance_2(node_shape, self.rect(), Point::from(*point)),
+            Geometry::Segment(from, to) => Line::new(from, to).distance_2(point),
+        }
     }
 
     fn contains_point(&self, point: &RPoint) -> bool {
-        Rectangle::from(self.envelope()).contains_point(point)
+        match self.geometry {
+            Geometry::Node(NodeShape::Rectangle) => Rectangle::from(self.envelope()).contains_point(point),
+            Geometry::Node(node_shape) => shape::contains(node_shape, self.rect(), Point::from(*point)),
+            // Segments have no area, so they can only be hit within a distance
+            Geometry::Segment(_, _) => false,
+        }
     }
 
     fn distance_2_if_less_or_equal(&self, point: &RPoint, max_distance_2: f64) -> Option<f64> {
-        Rectangle::from(self.envelope()).distance_2_if_less_or_equal(point, max_distance_2)
+        match self.geometry {
+            Geometry::Node(NodeShape::Rectangle) =>
+                Rectangle::from(self.envelope()).distance_2_if_less_or_equal(point, max_distance_2),
+            // The bounding box is never further away than the outline, so it rules most nodes out cheaply
+            Geometry::Node(node_shape) => Rectangle::from(self.envelope()).distance_2_if_less_or_equal(point, max_distance_2)
+                .map(|_| shape::distance_2(node_shape, self.rect(), Point::from(*point)))
+                .filter(|d| *d <= max_distance_2),
+            Geometry::Segment(from, to) => Some(Line::new(from, to).distance_2(point)).filter(|d| *d <= max_distance_2),
+        }
     }
 }
 
+/// Nodes and edges are kept in insertion order so paint order is stable and converting back into a
+/// [`Graph`] preserves the order of the graph it was created from.
 #[derive(Default)]
 pub struct DisplayGraph {
     rtree: RTree<RegionRef>,
-    nodes: HashMap<NodeId, DisplayNode>,
-    edges: HashMap<EdgeId, DisplayEdge>,
+    nodes: IndexMap<NodeId, DisplayNode>,
+    edges: IndexMap<EdgeId, DisplayEdge>,
     node_edges: HashMap<NodeId, Vec<EdgeId>>,
 }
 
 impl DisplayGraph {
-    pub(crate) fn add_node(&mut self, node: Node) {
+    /// Adds a node at the given position in the node order, such as where it was before it was removed
+    pub(crate) fn insert_node(&mut self, index: usize, node: Node) {
         let display_node = DisplayNode::from(&node);
         self.rtree.insert(RegionRef::from(&display_node));
-        self.nodes.insert(display_node.id, display_node);
+        self.nodes.shift_insert(index.min(self.nodes.len()), display_node.id, display_node);
     }
 
     pub(crate) fn nodes(&self) -> Vec<&DisplayNode> { self.nodes.values().collect() }
 
+    pub(crate) fn node_count(&self) -> usize { self.nodes.len() }
+
+    pub(crate) fn edge_count(&self) -> usize { self.edges.len() }
+
+    /// Position of a node in the node order
+    pub(crate) fn node_index(&self, node_id: &NodeId) -> Option<usize> {
+        self.nodes.get_index_of(node_id)
+    }
+
+    /// Position of an edge in the edge order
+    pub(crate) fn edge_index(&self, edge_id: &EdgeId) -> Option<usize> {
+        self.edges.get_index_of(edge_id)
+    }
+
+    pub(crate) fn edges(&self) -> Vec<&DisplayEdge> { self.edges.values().collect() }
+
     pub(crate) fn get_node(&self, node_id: &NodeId) -> Option<&DisplayNode> {
         self.nodes.get(node_id)
     }
-    pub(crate) fn get_mut_node(&mut self, node_id: &NodeId) -> Option<&mut DisplayNode> {
-        self.nodes.get_mut(node_id)
+
+    /// Replaces the contents of an existing node, keeping its edges and the spatial index in sync
+    pub(crate) fn update_node(&mut self, node: Node) {
+        if let Some(display_node) = self.nodes.get_mut(&node.id) {
+            display_node.text = node.text;
+            display_node.auto_size = node.auto_size;
+            display_node.shape = node.shape;
+            display_node.style = node.style;
+        }
+        self.set_node_rect(&node.id, node.rect);
     }
 
-    pub(crate) fn get_node_at_point(&self, point: RPoint) -> Option<&DisplayNode> {
-        if let Some(node_id) = self.get_node_id_at_point(&point) {
-            self.nodes.get(&node_id)
-        } else {
-            None
+    pub(crate) fn set_node_text(&mut self, node_id: &NodeId, text: String) {
+        if let Some(node) = self.nodes.get_mut(node_id) {
+            node.text = text;
         }
     }
-    pub(crate) fn get_mut_node_at_point(&mut self, point: RPoint) -> Option<&mut DisplayNode> {
+
+    pub(crate) fn get_node_at_point(&self, point: RPoint) -> Option<&DisplayNode> {
         if let Some(node_id) = self.get_node_id_at_point(&point) {
-            self.nodes.get_mut(&node_id)
+            self.nodes.get(&node_id)
         } else {
             None
         }
     }
 
     pub(crate) fn translate_node(&mut self, node_id: &NodeId, translation: Vec2) {
-        // Update node with translation & reinsert it
-        // TODO: Is there a nicer way to phrase this?
-        let new_node_center = {
-            let target_node = self.get_mut_node(node_id).unwrap();
-            target_node.rect = target_node.rect.with_origin(target_node.rect.origin() + translation);
-            let new_node_center = (&target_node).rect.center().clone();
-            let node_region_ref = RegionRef::from(&*target_node);
-            self.rtree.insert(node_region_ref);
-            new_node_center
-        };
-
-        // TODO: Surely there's a better way to do this
-        const EMPTY_EDGE_LIST: &Vec<EdgeId> = &vec![];
-        // TODO: This is absurd I should find a way to avoid having to copy this vec
-        let affected_edge_ids = self.node_edges.get(node_id).unwrap_or(EMPTY_EDGE_LIST).clone();
-        // Remove affected node and connected edges from the R-Tree
-        self.rtree.remove(&RegionRef::from(self.get_node(node_id).unwrap()));
-        for edge_id in &affected_edge_ids {
-            self.rtree.remove(&RegionRef::from(self.edges.get(&edge_id).unwrap()));
-        }
-        // Update the edges and reinsert them into the R-Tree
-        for edge_id in &affected_edge_ids {
-            let moved_edge = self.edges.get_mut(&edge_id).unwrap();
-            if &moved_edge.from_node == node_id {
-                moved_edge.start_point = new_node_center.clone();
-            } else if &moved_edge.to_node == node_id {
-                moved_edge.end_point = new_node_center.clone();
-            }
-            self.rtree.insert(RegionRef::from(&*moved_edge));
+        if let Some(node) = self.nodes.get(node_id) {
+            self.set_node_rect(node_id, node.rect + translation);
         }
     }
 
-    pub(crate) fn add_edge(&mut self, edge: Edge) {
-        let display_edge = DisplayEdge::new(&edge, self.get_node_center(&edge.from_node_id),
-                                            self.get_node_center(&edge.to_node_id));
+    /// The only way a node's geometry changes, so its index entry and edges always follow it.
+    /// Mutable access to nodes is deliberately not exposed, as changing a rect without going
+    /// through here would leave a stale envelope in the R-tree.
+    pub(crate) fn set_node_rect(&mut self, node_id: &NodeId, rect: Rect) {
+        let Some(node) = self.nodes.get_mut(node_id) else { return };
+        // The R-tree finds entries by envelope, so the old entry must be removed before the rect changes
+        self.rtree.remove(&RegionRef::from(&*node));
+        node.rect = rect;
+        self.rtree.insert(RegionRef::from(&*node));
+        // Both ends of each connected edge can move as the edge is clipped to the node borders
+        self.reposition_edges(node_id);
+    }
+
+    /// Copies of the given nodes moved by `offset`, along with copies of every edge connected to them
+    /// clipped to the moved nodes, both in insertion order. Used to preview a move without touching
+    /// the graph or its index.
+    pub(crate) fn translation_preview(&self, node_ids: &HashSet<NodeId>, offset: Vec2) -> (Vec<DisplayNode>, Vec<DisplayEdge>) {
+        let mut nodes: Vec<(usize, &DisplayNode)> = node_ids.iter()
+            .filter_map(|node_id| self.nodes.get_full(node_id).map(|(i, _, n)| (i, n)))
+            .collect();
+        nodes.sort_unstable_by_key(|(i, _)| *i);
+        let moved_nodes: IndexMap<NodeId, DisplayNode> = nodes.into_iter()
+            .map(|(_, n)| (n.id, DisplayNode { rect: n.rect + offset, ..n.clone() }))
+            .collect();
+
+        let edge_ids: HashSet<&EdgeId> = moved_nodes.keys().flat_map(|node_id| self.node_edge_ids(node_id)).collect();
+        let mut edges: Vec<(usize, &DisplayEdge)> = edge_ids.into_iter()
+            .filter_map(|edge_id| self.edges.get_full(edge_id).map(|(i, _, e)| (i, e)))
+            .collect();
+        edges.sort_unstable_by_key(|(i, _)| *i);
+        let moved_edges = edges.into_iter().map(|(_, e)| {
+            let from = moved_nodes.get(&e.from_node).unwrap_or(&self.nodes[&e.from_node]);
+            let to = moved_nodes.get(&e.to_node).unwrap_or(&self.nodes[&e.to_node]);
+            let (start_point, end_point) = edge::clipped_endpoints(from, to);
+            DisplayEdge { start_point, end_point, ..e.clone() }
+        }).collect();
+        (moved_nodes.into_values().collect(), moved_edges)
+    }
+
+    /// Adds an edge at the given position in the edge order, such as where it was before it was removed
+    pub(crate) fn insert_edge(&mut self, index: usize, edge: Edge) {
+        let (start_point, end_point) = self.edge_endpoints(&edge.from_node_id, &edge.to_node_id);
+        let display_edge = DisplayEdge::new(&edge, start_point, end_point);
         self.rtree.insert(RegionRef::from(&display_edge));
-        self.edges.insert(display_edge.id, display_edge);
+        self.edges.shift_insert(index.min(self.edges.len()), display_edge.id, display_edge);
         self.node_edges.entry(edge.from_node_id).and_modify(|vec| vec.push(edge.id)).or_insert(vec![edge.id]);
         self.node_edges.entry(edge.to_node_id).and_modify(|vec| vec.push(edge.id)).or_insert(vec![edge.id]);
     }
 
-    pub(crate) fn edges(&self) -> Vec<&DisplayEdge> {
-        self.edges.values().collect()
+    pub(crate) fn get_edge(&self, edge_id: &EdgeId) -> Option<&DisplayEdge> {
+        self.edges.get(edge_id)
+    }
+
+    /// Replaces the properties of an existing edge - the nodes an edge connects can't be changed
+    pub(crate) fn update_edge(&mut self, edge: Edge) {
+        if let Some(display_edge) = self.edges.get_mut(&edge.id) {
+            display_edge.edge_type = edge.edge_type;
+            display_edge.style = edge.style;
+        }
+    }
+
+    /// IDs of every edge starting or ending at the given node
+    pub(crate) fn node_edge_ids(&self, node_id: &NodeId) -> &[EdgeId] {
+        self.node_edges.get(node_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn contains(&self, element_id: &ElementId) -> bool {
+        match element_id {
+            ElementId::Node(node_id) => self.nodes.contains_key(node_id),
+            ElementId::Edge(edge_id) => self.edges.contains_key(edge_id),
+        }
+    }
+
+    /// Removes a node along with every edge connected to it
+    pub(crate) fn remove_node(&mut self, node_id: &NodeId) -> Option<DisplayNode> {
+        let node = self.nodes.shift_remove(node_id)?;
+        self.rtree.remove(&RegionRef::from(&node));
+        for edge_id in self.node_edges.remove(node_id).unwrap_or_default() {
+            self.remove_edge(&edge_id);
+        }
+        Some(node)
+    }
+
+    pub(crate) fn remove_edge(&mut self, edge_id: &EdgeId) -> Option<DisplayEdge> {
+        let edge = self.edges.shift_remove(edge_id)?;
+        self.rtree.remove(&RegionRef::from(&edge));
+        for node_id in [edge.from_node, edge.to_node] {
+            if let Some(edge_ids) = self.node_edges.get_mut(&node_id) {
+                edge_ids.retain(|id| id != edge_id);
+            }
+        }
+        Some(edge)
+    }
+
+    /// Moves the ends of a node's edges to follow the node
+    fn reposition_edges(&mut self, node_id: &NodeId) {
+        for edge_id in self.node_edge_ids(node_id).to_vec() {
+            let (start_point, end_point) = {
+                let edge = &self.edges[&edge_id];
+                self.edge_endpoints(&edge.from_node, &edge.to_node)
+            };
+            let edge = self.edges.get_mut(&edge_id).unwrap();
+            self.rtree.remove(&RegionRef::from(&*edge));
+            edge.start_point = start_point;
+            edge.end_point = end_point;
+            self.rtree.insert(RegionRef::from(&*edge));
+        }
     }
 
-    fn get_node_center(&self, node_id: &NodeId) -> Point {
-        self.nodes.get(node_id).unwrap().rect.center()
+    fn edge_endpoints(&self, from_node_id: &NodeId, to_node_id: &NodeId) -> (Point, Point) {
+        edge::clipped_endpoints(&self.nodes[from_node_id], &self.nodes[to_node_id])
+    }
+
+    /// Elements within a rect. When `crossing` is set, anything touching the rect is included,
+    /// otherwise only elements entirely inside it are.
+    pub(crate) fn elements_in_rect(&self, rect: Rect, crossing: bool) -> Vec<ElementId> {
+        let envelope = Self::envelope(rect);
+        if crossing {
+            self.rtree.locate_in_envelope_intersecting(&envelope)
+                .filter(|r| match r.id {
+                    // An edge's bounding box can touch the rect without the line itself doing so
+                    ElementId::Edge(edge_id) => self.edges[&edge_id].intersects_rect(rect),
+                    ElementId::Node(_) => true,
+                })
+                .map(|r| r.id).collect()
+        } else {
+            self.rtree.locate_in_envelope(&envelope).map(|r| r.id).collect()
+        }
+    }
+
+    /// Nodes whose rects intersect `rect`, in insertion order
+    pub(crate) fn nodes_intersecting(&self, rect: Rect) -> Vec<&DisplayNode> {
+        let mut nodes: Vec<(usize, &DisplayNode)> = self.rtree.locate_in_envelope_intersecting(&Self::envelope(rect))
+            .filter_map(|r| match r.id {
+                ElementId::Node(node_id) => self.nodes.get_full(&node_id).map(|(i, _, n)| (i, n)),
+                _ => None
+            })
+            .collect();
+        nodes.sort_unstable_by_key(|(i, _)| *i);
+        nodes.into_iter().map(|(_, n)| n).collect()
+    }
+
+    /// Edges whose bounding boxes intersect `rect`, in insertion order
+    pub(crate) fn edges_intersecting(&self, rect: Rect) -> Vec<&DisplayEdge> {
+        let mut edges: Vec<(usize, &DisplayEdge)> = self.rtree.locate_in_envelope_intersecting(&Self::envelope(rect))
+            .filter_map(|r| match r.id {
+                ElementId::Edge(edge_id) => self.edges.get_full(&edge_id).map(|(i, _, e)| (i, e)),
+                _ => None
+            })
+            .collect();
+        edges.sort_unstable_by_key(|(i, _)| *i);
+        edges.into_iter().map(|(_, e)| e).collect()
+    }
+
+    fn envelope(rect: Rect) -> AABB<RPoint> {
+        AABB::from_corners((rect.x0, rect.y0), (rect.x1, rect.y1))
+    }
+
+    /// Nodes whose shapes are no further than `distance` from a point
+    pub(crate) fn nodes_within_distance(&self, point: Point, distance: f64) -> Vec<&DisplayNode> {
+        self.rtree.locate_within_distance((point.x, point.y), distance * distance)
+            .filter_map(|r| match r.id {
+                ElementId::Node(node_id) => self.nodes.get(&node_id),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// The edge closest to a point, as long as it's no further away than `max_distance`
+    pub(crate) fn get_edge_near_point(&self, point: RPoint, max_distance: f64) -> Option<&DisplayEdge> {
+        self.rtree.locate_within_distance(point, max_distance * max_distance)
+            .filter_map(|r| match r.id {
+                ElementId::Edge(edge_id) => Some((edge_id, r.distance_2(&point))),
+                _ => None
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .and_then(|(edge_id, _)| self.edges.get(&edge_id))
     }
 
     fn get_node_id_at_point(&self, point: &RPoint) -> Option<NodeId> {
@@ -168,14 +365,16 @@ impl DisplayGraph {
 impl From<&Graph> for DisplayGraph {
     fn from(g: &Graph) -> Self {
         let display_nodes: Vec<DisplayNode> = g.nodes.iter().map(DisplayNode::from).collect();
-        let mut region_refs: Vec<RegionRef> = (&display_nodes).iter().map(RegionRef::from).collect();
+        let mut region_refs: Vec<RegionRef> = display_nodes.iter().map(RegionRef::from).collect();
 
-        let node_map: HashMap<NodeId, DisplayNode> =
+        let node_map: IndexMap<NodeId, DisplayNode> =
             display_nodes.into_iter().map(|n| (n.id, n)).collect();
 
-        let display_edges: Vec<DisplayEdge> = g.edges.iter().map(|e|
-            DisplayEdge::new(e, node_map.get(&e.from_node_id).unwrap().rect.center(),
-                             node_map.get(&e.to_node_id).unwrap().rect.center())).collect();
+        let display_edges: Vec<DisplayEdge> = g.edges.iter().map(|e| {
+            let (start_point, end_point) =
+                edge::clipped_endpoints(&node_map[&e.from_node_id], &node_map[&e.to_node_id]);
+            DisplayEdge::new(e, start_point, end_point)
+        }).collect();
 
         let mut node_edges: HashMap<NodeId, Vec<EdgeId>> = HashMap::new();
         for edge in &g.edges {
@@ -191,3 +390,309 @@ impl From<&Graph> for DisplayGraph {
         }
     }
 }
+
+impl From<&DisplayGraph> for Graph {
+    fn from(g: &DisplayGraph) -> Self {
+        Graph {
+            nodes: g.nodes.values().map(Node::from).collect(),
+            edges: g.edges.values().map(Edge::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Point, Rect, Vec2};
+
+    use crate::graph::edge::EdgeType;
+    use crate::graph::node::NodeShape;
+    use crate::graph_view::test_util::{edge, node};
+
+    use super::*;
+
+    const NODE_SIZE: (f64, f64) = (120.0, 60.0);
+
+    fn sample_graph() -> Graph {
+        let a = Node { text: String::from("a"), ..node((0.0, 0.0), NODE_SIZE) };
+        let b = Node { text: String::from("b"), ..node((200.0, 40.0), NODE_SIZE) };
+        let c = Node { text: String::from("multi\nline"), ..node((-150.5, 310.25), NODE_SIZE) };
+        let edges = vec![edge(&a, &b), edge(&b, &c), edge(&c, &a)];
+        Graph { nodes: vec![a, b, c], edges }
+    }
+
+    #[test]
+    fn graph_round_trips_through_display_graph() {
+        let graph = sample_graph();
+        assert_eq!(Graph::from(&DisplayGraph::from(&graph)), graph);
+    }
+
+    #[test]
+    fn empty_graph_round_trips_through_display_graph() {
+        assert_eq!(Graph::from(&DisplayGraph::from(&Graph::default())), Graph::default());
+    }
+
+    #[test]
+    fn display_graph_edits_are_kept_when_converting_back() {
+        let mut display_graph = DisplayGraph::from(&sample_graph());
+        let new_node = node((500.0, 500.0), NODE_SIZE);
+        let moved_node_id = display_graph.nodes[0].id;
+        let new_edge = edge(&new_node, &Node::from(&display_graph.nodes[1]));
+        display_graph.insert_node(display_graph.node_count(), new_node.clone());
+        display_graph.insert_edge(display_graph.edge_count(), new_edge.clone());
+        display_graph.translate_node(&moved_node_id, Vec2::new(10.0, -20.0));
+
+        let graph = Graph::from(&display_graph);
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(graph.nodes[0].rect.origin(), Point::new(10.0, -20.0));
+        assert_eq!(graph.nodes[3], new_node);
+        assert_eq!(graph.edges.len(), 4);
+        assert_eq!(graph.edges[3], new_edge);
+        assert_eq!(Graph::from(&DisplayGraph::from(&graph)), graph);
+    }
+
+    #[test]
+    fn edges_end_at_node_borders() {
+        let a = node((0.0, 0.0), NODE_SIZE);
+        let b = node((300.0, 0.0), NODE_SIZE);
+        let ab = edge(&a, &b);
+        let mut display_graph = DisplayGraph::from(&Graph { nodes: vec![a.clone(), b.clone()], edges: vec![ab.clone()] });
+        let display_edge = display_graph.get_edge(&ab.id).unwrap();
+        assert_eq!(display_edge.start_point, Point::new(120.0, 30.0));
+        assert_eq!(display_edge.end_point, Point::new(300.0, 30.0));
+
+        // Move b directly below a so the edge leaves through the bottom and top borders
+        display_graph.translate_node(&b.id, Vec2::new(-300.0, 200.0));
+        let display_edge = display_graph.get_edge(&ab.id).unwrap();
+        assert_eq!(display_edge.start_point, Point::new(60.0, 60.0));
+        assert_eq!(display_edge.end_point, Point::new(60.0, 200.0));
+    }
+
+    #[test]
+    fn edges_are_hit_tested_against_the_line_not_its_bounding_box() {
+        let a = node((0.0, 0.0), NODE_SIZE);
+        let b = node((400.0, 400.0), NODE_SIZE);
+        let ab = edge(&a, &b);
+        let display_graph = DisplayGraph::from(&Graph { nodes: vec![a, b], edges: vec![ab.clone()] });
+        let display_edge = display_graph.get_edge(&ab.id).unwrap();
+        let midpoint = display_edge.start_point.midpoint(display_edge.end_point);
+
+        assert_eq!(display_graph.get_edge_near_point((midpoint.x + 3.0, midpoint.y), 5.0).map(|e| e.id), Some(ab.id));
+        // Inside the edge's bounding box but well away from the line itself
+        assert!(display_graph.get_edge_near_point((display_edge.end_point.x - 10.0, display_edge.start_point.y + 10.0), 5.0).is_none());
+        assert!(display_graph.get_edge_near_point((midpoint.x + 10.0, midpoint.y), 5.0).is_none());
+    }
+
+    #[test]
+    fn elements_in_rect_can_require_containment_or_crossing() {
+        let a = node((0.0, 0.0), NODE_SIZE);
+        let b = node((400.0, 400.0), NODE_SIZE);
+        let ab = edge(&a, &b);
+        let display_graph = DisplayGraph::from(&Graph { nodes: vec![a.clone(), b.clone()], edges: vec![ab.clone()] });
+
+        let around_a = Rect::new(-10.0, -10.0, 130.0, 70.0);
+        assert_eq!(display_graph.elements_in_rect(around_a, false), vec![ElementId::Node(a.id)]);
+        let mut crossing = display_graph.elements_in_rect(around_a, true);
+        crossing.sort_by_key(|id| matches!(id, ElementId::Edge(_)));
+        assert_eq!(crossing, vec![ElementId::Node(a.id), ElementId::Edge(ab.id)]);
+
+        // Touches the edge's bounding box but not the line
+        let off_the_line = Rect::new(300.0, 100.0, 350.0, 150.0);
+        assert!(display_graph.elements_in_rect(off_the_line, true).is_empty());
+    }
+
+    #[test]
+    fn moved_nodes_are_hit_tested_at_their_new_position() {
+        let a = node((0.0, 0.0), NODE_SIZE);
+        let mut display_graph = DisplayGraph::from(&Graph { nodes: vec![a.clone()], edges: vec![] });
+        display_graph.translate_node(&a.id, Vec2::new(50.0, 0.0));
+        display_graph.translate_node(&a.id, Vec2::new(50.0, 0.0));
+
+        assert!(display_graph.get_node_at_point((10.0, 10.0)).is_none());
+        assert_eq!(display_graph.get_node_at_point((200.0, 10.0)).map(|n| n.id), Some(a.id));
+        assert_eq!(display_graph.rtree.size(), 1);
+    }
+
+    #[test]
+    fn shaped_nodes_are_hit_tested_and_clipped_to_their_outline() {
+        let a = node((0.0, 0.0), NODE_SIZE);
+        let b = node((240.0, 0.0), NODE_SIZE);
+        let edge = Edge::new(a.id, b.id, EdgeType::Undirected);
+        let mut display_graph = DisplayGraph::from(&Graph { nodes: vec![a.clone(), b.clone()], edges: vec![edge.clone()] });
+        assert_eq!(display_graph.get_node_at_point((2.0, 2.0)).map(|n| n.id), Some(a.id));
+        assert_eq!(display_graph.get_edge(&edge.id).unwrap().end_point, Point::new(240.0, 30.0));
+
+        display_graph.update_node(Node { shape: NodeShape::Diamond, ..a.clone() });
+        display_graph.update_node(Node { shape: NodeShape::Ellipse, ..b.clone() });
+        assert!(display_graph.get_node_at_point((2.0, 2.0)).is_none());
+        assert_eq!(display_graph.get_node_at_point((60.0, 30.0)).map(|n| n.id), Some(a.id));
+        // The edge runs between the centres, so it meets both outlines at their sides
+        let edge = display_graph.get_edge(&edge.id).unwrap();
+        assert!((edge.start_point - Point::new(120.0, 30.0)).hypot() < 1e-9);
+        assert!((edge.end_point - Point::new(240.0, 30.0)).hypot() < 0.1);
+        assert_eq!(display_graph.nodes_within_distance(Point::new(0.0, 0.0), 5.0).len(), 0);
+    }
+
+    #[test]
+    fn only_elements_in_view_are_returned_in_insertion_order() {
+        let graph = sample_graph();
+        let display_graph = DisplayGraph::from(&graph);
+        let view = Rect::new(-10.0, -10.0, 400.0, 200.0);
+        let node_ids: Vec<NodeId> = display_graph.nodes_intersecting(view).iter().map(|n| n.id).collect();
+        assert_eq!(node_ids, vec![graph.nodes[0].id, graph.nodes[1].id]);
+        // c is out of view, but its edges to a and b pass through it
+        let edge_ids: Vec<EdgeId> = display_graph.edges_intersecting(view).iter().map(|e| e.id).collect();
+        assert_eq!(edge_ids, graph.edges.iter().map(|e| e.id).collect::<Vec<_>>());
+        assert!(display_graph.nodes_intersecting(Rect::new(1000.0, 1000.0, 2000.0, 2000.0)).is_empty());
+    }
+
+    #[test]
+    fn translation_preview_matches_translating_without_changing_the_graph() {
+        let graph = sample_graph();
+        let moved_ids: HashSet<NodeId> = [graph.nodes[0].id, graph.nodes[2].id].into_iter().collect();
+        let offset = Vec2::new(35.0, -12.5);
+        let mut display_graph = DisplayGraph::from(&graph);
+        let (preview_nodes, preview_edges) = display_graph.translation_preview(&moved_ids, offset);
+        assert_eq!(Graph::from(&display_graph), graph);
+
+        for node_id in &moved_ids {
+            display_graph.translate_node(node_id, offset);
+        }
+        assert_eq!(preview_nodes.iter().map(Node::from).collect::<Vec<_>>(),
+                   vec![Node::from(&display_graph.nodes[0]), Node::from(&display_graph.nodes[2])]);
+        // Every edge touches a moved node
+        assert_eq!(preview_edges.len(), 3);
+        for (preview, translated) in preview_edges.iter().zip(display_graph.edges.values()) {
+            assert_eq!((preview.id, preview.start_point, preview.end_point),
+                       (translated.id, translated.start_point, translated.end_point));
+        }
+    }
+
+    #[test]
+    fn removing_a_node_removes_its_edges() {
+        let graph = sample_graph();
+        let removed_node_id = graph.nodes[1].id;
+        let mut display_graph = DisplayGraph::from(&graph);
+        assert!(display_graph.remove_node(&removed_node_id).is_some());
+
+        let remaining_edge_id = graph.edges[2].id;
+        assert_eq!(display_graph.edges.keys().collect::<Vec<_>>(), vec![&remaining_edge_id]);
+        assert!(!display_graph.node_edges.contains_key(&removed_node_id));
+        for edge_ids in display_graph.node_edges.values() {
+            assert_eq!(edge_ids, &vec![remaining_edge_id]);
+        }
+        let indexed_ids: Vec<ElementId> = display_graph.rtree.iter().map(|r| r.id).collect();
+        assert_eq!(indexed_ids.len(), 3);
+        assert!(!indexed_ids.contains(&ElementId::Node(removed_node_id)));
+        assert!(indexed_ids.contains(&ElementId::Edge(remaining_edge_id)));
+    }
+}
+
+/// Property tests for the spatial index - each element must have exactly one entry in the R-tree,
+/// and that entry's envelope must match the element's current geometry.
+#[cfg(test)]
+mod index_tests {
+    use std::collections::HashMap;
+
+    use druid::{Point, Rect, Size, Vec2};
+    use proptest::prelude::*;
+
+    use crate::graph::edge::EdgeType;
+
+    use super::*;
+
+    /// Mutations refer to elements by index into the graph's current nodes or edges, wrapping around
+    #[derive(Clone, Debug)]
+    enum Mutation {
+        AddNode(i32, i32),
+        AddEdge(usize, usize),
+        TranslateNode(usize, i32, i32),
+        ResizeNode(usize, u16, u16),
+        RemoveNode(usize),
+        RemoveEdge(usize),
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        prop_oneof![
+            (-1000..1000, -1000..1000).prop_map(|(x, y)| Mutation::AddNode(x, y)),
+            (any::<usize>(), any::<usize>()).prop_map(|(from, to)| Mutation::AddEdge(from, to)),
+            (any::<usize>(), -300..300, -300..300).prop_map(|(i, dx, dy)| Mutation::TranslateNode(i, dx, dy)),
+            (any::<usize>(), 10..400u16, 10..400u16).prop_map(|(i, w, h)| Mutation::ResizeNode(i, w, h)),
+            any::<usize>().prop_map(Mutation::RemoveNode),
+            any::<usize>().prop_map(Mutation::RemoveEdge),
+        ]
+    }
+
+    fn apply(display_graph: &mut DisplayGraph, mutation: &Mutation) {
+        let node_ids: Vec<NodeId> = display_graph.nodes.keys().copied().collect();
+        let edge_ids: Vec<EdgeId> = display_graph.edges.keys().copied().collect();
+        let nth_node = |i: usize| node_ids.get(i % node_ids.len().max(1)).copied();
+        match *mutation {
+            Mutation::AddNode(x, y) => display_graph.insert_node(display_graph.node_count(), Node::new(Point::new(x as f64, y as f64), None)),
+            Mutation::AddEdge(from, to) => {
+                if let (Some(from), Some(to)) = (nth_node(from), nth_node(to)) {
+                    if from != to {
+                        display_graph.insert_edge(display_graph.edge_count(), Edge::new(from, to, EdgeType::Directional));
+                    }
+                }
+            }
+            Mutation::TranslateNode(i, dx, dy) => {
+                if let Some(node_id) = nth_node(i) {
+                    display_graph.translate_node(&node_id, Vec2::new(dx as f64, dy as f64));
+                }
+            }
+            Mutation::ResizeNode(i, width, height) => {
+                if let Some(node_id) = nth_node(i) {
+                    let mut node = Node::from(&display_graph.nodes[&node_id]);
+                    node.rect = Rect::from_center_size(node.rect.center(), Size::new(width as f64, height as f64));
+                    display_graph.update_node(node);
+                }
+            }
+            Mutation::RemoveNode(i) => {
+                if let Some(node_id) = nth_node(i) {
+                    display_graph.remove_node(&node_id);
+                }
+            }
+            Mutation::RemoveEdge(i) => {
+                if !edge_ids.is_empty() {
+                    display_graph.remove_edge(&edge_ids[i % edge_ids.len()]);
+                }
+            }
+        }
+    }
+
+    fn assert_index_is_current(display_graph: &DisplayGraph) {
+        let mut expected: HashMap<ElementId, AABB<RPoint>> = HashMap::new();
+        for node in display_graph.nodes.values() {
+            expected.insert(ElementId::Node(node.id), RegionRef::from(node).region);
+        }
+        for edge in display_graph.edges.values() {
+            let (start_point, end_point) = display_graph.edge_endpoints(&edge.from_node, &edge.to_node);
+            assert_eq!((edge.start_point, edge.end_point), (start_point, end_point), "edge endpoints are stale");
+            expected.insert(ElementId::Edge(edge.id), RegionRef::from(edge).region);
+        }
+        let indexed: HashMap<ElementId, AABB<RPoint>> = display_graph.rtree.iter().map(|r| (r.id, r.region)).collect();
+        assert_eq!(display_graph.rtree.size(), indexed.len(), "an element is indexed more than once");
+        assert_eq!(indexed, expected);
+
+        for (node_id, edge_ids) in &display_graph.node_edges {
+            assert!(display_graph.nodes.contains_key(node_id), "adjacency refers to a removed node");
+            for edge_id in edge_ids {
+                let edge = &display_graph.edges[edge_id];
+                assert!(edge.from_node == *node_id || edge.to_node == *node_id);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn rtree_matches_elements_after_mutations(mutations in prop::collection::vec(mutation(), 1..60)) {
+            let mut display_graph = DisplayGraph::default();
+            for mutation in &mutations {
+                apply(&mut display_graph, mutation);
+                assert_index_is_current(&display_graph);
+            }
+            let round_tripped = DisplayGraph::from(&Graph::from(&display_graph));
+            assert_index_is_current(&round_tripped);
+        }
+    }
+}
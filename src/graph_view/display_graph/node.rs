@@ -1,12 +1,16 @@
 use druid::Rect;
 
-use crate::graph::node::{Node, NodeId};
+use crate::graph::node::{AutoSize, Node, NodeId, NodeShape};
+use crate::graph::style::NodeStyle;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct DisplayNode {
     pub id: NodeId,
     pub text: String,
     pub rect: Rect,
+    pub auto_size: AutoSize,
+    pub shape: NodeShape,
+    pub style: NodeStyle,
 }
 
 impl From<&Node> for DisplayNode {
@@ -15,6 +19,22 @@ impl From<&Node> for DisplayNode {
             id: node.id,
             text: node.text.clone(),
             rect: node.rect,
+            auto_size: node.auto_size,
+            shape: node.shape,
+            style: node.style.clone(),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<&DisplayNode> for Node {
+    fn from(node: &DisplayNode) -> Self {
+        Node {
+            id: node.id,
+            text: node.text.clone(),
+            rect: node.rect,
+            auto_size: node.auto_size,
+            shape: node.shape,
+            style: node.style.clone(),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::f64::consts::TAU;
+
+use druid::{Point, Rect, Vec2};
+use druid::kurbo::{BezPath, Ellipse, PathEl, RoundedRect, Shape};
+
+use crate::graph::node::NodeShape;
+
+/// How closely curves are followed when outlines are built and flattened, in the units of the rect
+const TOLERANCE: f64 = 0.1;
+/// Bumps around a cloud, a multiple of four so there's one at the top, bottom and each side
+const CLOUD_BUMPS: usize = 12;
+
+/// Outline of a shape stretched to fill `rect`. Only proportions of the rect are used,
+/// so the outline of a rect on screen is the same as the scene outline transformed onto the screen.
+pub(crate) fn outline(shape: NodeShape, rect: Rect) -> BezPath {
+    match shape {
+        NodeShape::Rectangle => rect.to_path(TOLERANCE),
+        NodeShape::RoundedRect | NodeShape::Pill => RoundedRect::from_rect(rect, corner_radius(shape, rect)).to_path(TOLERANCE),
+        NodeShape::Ellipse => Ellipse::from_rect(rect).to_path(TOLERANCE),
+        NodeShape::Diamond | NodeShape::Hexagon => polygon_path(&corners(shape, rect)),
+        NodeShape::Cloud => cloud(rect),
+    }
+}
+
+fn corner_radius(shape: NodeShape, rect: Rect) -> f64 {
+    let short_side = rect.width().min(rect.height());
+    match shape {
+        NodeShape::Pill => short_side / 2.0,
+        _ => short_side / 5.0,
+    }
+}
+
+/// Corners of a hexagon, or otherwise of a diamond, clockwise from the top
+fn corners(shape: NodeShape, rect: Rect) -> Vec<Point> {
+    let center = rect.center();
+    match shape {
+        NodeShape::Hexagon => {
+            // Sides slope at 60 degrees unless the rect is too narrow for that
+            let inset = (rect.height() / 2.0 / 3f64.sqrt()).min(rect.width() / 4.0);
+            vec![
+                Point::new(rect.x0 + inset, rect.y0),
+                Point::new(rect.x1 - inset, rect.y0),
+                Point::new(rect.x1, center.y),
+                Point::new(rect.x1 - inset, rect.y1),
+                Point::new(rect.x0 + inset, rect.y1),
+                Point::new(rect.x0, center.y),
+            ]
+        }
+        _ => vec![
+            Point::new(center.x, rect.y0),
+            Point::new(rect.x1, center.y),
+            Point::new(center.x, rect.y1),
+            Point::new(rect.x0, center.y),
+        ],
+    }
+}
+
+fn polygon_path(points: &[Point]) -> BezPath {
+    let mut path = BezPath::new();
+    path.move_to(points[0]);
+    for point in &points[1..] {
+        path.line_to(*point);
+    }
+    path.close_path();
+    path
+}
+
+/// Bumps that start and end on an ellipse inside the rect and peak just inside the rect's own ellipse
+fn cloud(rect: Rect) -> BezPath {
+    const VALLEY: f64 = 0.8;
+    const PEAK: f64 = 0.97;
+    let center = rect.center();
+    let on_ellipse = |angle: f64, scale: f64| {
+        center + Vec2::new(rect.width() / 2.0 * angle.cos(), rect.height() / 2.0 * angle.sin()) * scale
+    };
+    let step = TAU / CLOUD_BUMPS as f64;
+    let mut path = BezPath::new();
+    path.move_to(on_ellipse(-step / 2.0, VALLEY));
+    for i in 0..CLOUD_BUMPS {
+        let angle = i as f64 * step;
+        // A quadratic curve passes halfway between its control point and the midpoint of its ends
+        let ends_midpoint = on_ellipse(angle, VALLEY * (step / 2.0).cos());
+        let peak = on_ellipse(angle, PEAK);
+        path.quad_to(peak + (peak - ends_midpoint), on_ellipse(angle + step / 2.0, VALLEY));
+    }
+    path.close_path();
+    path
+}
+
+/// Outline of a shape as a closed polygon. Curved outlines are flattened, which is slow enough that
+/// it's only done for shapes that can't be measured directly.
+fn polygon(shape: NodeShape, rect: Rect) -> Vec<Point> {
+    if let NodeShape::Diamond | NodeShape::Hexagon = shape {
+        return corners(shape, rect);
+    }
+    let mut points = vec![];
+    outline(shape, rect).flatten(TOLERANCE, |el| match el {
+        PathEl::MoveTo(p) | PathEl::LineTo(p) => points.push(p),
+        _ => {}
+    });
+    points
+}
+
+fn polygon_edges(points: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    points.iter().zip(points.iter().cycle().skip(1)).map(|(a, b)| (*a, *b))
+}
+
+fn polygon_contains(points: &[Point], point: Point) -> bool {
+    // Even-odd rule: count the polygon edges crossed by a ray from the point to the right
+    polygon_edges(points)
+        .filter(|(a, b)| (a.y > point.y) != (b.y > point.y))
+        .filter(|(a, b)| point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x))
+        .count() % 2 == 1
+}
+
+fn polygon_distance_2(points: &[Point], point: Point) -> f64 {
+    polygon_edges(points)
+        .map(|(a, b)| {
+            let ab = b - a;
+            let t = if ab.hypot2() == 0.0 { 0.0 } else { ((point - a).dot(ab) / ab.hypot2()).clamp(0.0, 1.0) };
+            (a + ab * t - point).hypot2()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Offset of a point from the corner circle of a rounded rect nearest to it, or zero along an axis where the
+/// point is level with the straight part of a side
+fn corner_offset(rect: Rect, radius: f64, point: Point) -> Vec2 {
+    let from_center = point - rect.center();
+    Vec2::new(
+        (from_center.x.abs() - (rect.width() / 2.0 - radius)).max(0.0),
+        (from_center.y.abs() - (rect.height() / 2.0 - radius)).max(0.0),
+    )
+}
+
+pub(crate) fn contains(shape: NodeShape, rect: Rect, point: Point) -> bool {
+    if !rect.contains(point) {
+        return false;
+    }
+    // Offset from the centre as a fraction of the distance to the sides
+    let normalized = point - rect.center();
+    let normalized = Vec2::new(normalized.x / (rect.width() / 2.0), normalized.y / (rect.height() / 2.0));
+    match shape {
+        NodeShape::Rectangle => true,
+        NodeShape::Ellipse => normalized.hypot2() <= 1.0,
+        NodeShape::Diamond => normalized.x.abs() + normalized.y.abs() <= 1.0,
+        NodeShape::RoundedRect | NodeShape::Pill => {
+            let radius = corner_radius(shape, rect);
+            corner_offset(rect, radius, point).hypot() <= radius
+        }
+        NodeShape::Hexagon | NodeShape::Cloud => polygon_contains(&polygon(shape, rect), point),
+    }
+}
+
+/// Squared distance from a point to the nearest part of a shape, which is zero inside it
+pub(crate) fn distance_2(shape: NodeShape, rect: Rect, point: Point) -> f64 {
+    if contains(shape, rect, point) {
+        return 0.0;
+    }
+    match shape {
+        NodeShape::Rectangle => {
+            let dx = (rect.x0 - point.x).max(point.x - rect.x1).max(0.0);
+            let dy = (rect.y0 - point.y).max(point.y - rect.y1).max(0.0);
+            dx * dx + dy * dy
+        }
+        NodeShape::Ellipse => ellipse_distance_2(rect, point),
+        NodeShape::RoundedRect | NodeShape::Pill => {
+            let radius = corner_radius(shape, rect);
+            (corner_offset(rect, radius, point).hypot() - radius).max(0.0).powi(2)
+        }
+        NodeShape::Diamond | NodeShape::Hexagon | NodeShape::Cloud => polygon_distance_2(&polygon(shape, rect), point),
+    }
+}
+
+/// Squared distance from a point outside an ellipse to the ellipse, found by repeatedly moving a
+/// point on the ellipse towards the closest one. Three steps are enough to be well within `TOLERANCE`.
+fn ellipse_distance_2(rect: Rect, point: Point) -> f64 {
+    let (a, b) = (rect.width() / 2.0, rect.height() / 2.0);
+    // By symmetry, the closest point is in the same quadrant, so work in the positive one
+    let offset = point - rect.center();
+    let (px, py) = (offset.x.abs(), offset.y.abs());
+    let (mut tx, mut ty) = (std::f64::consts::FRAC_1_SQRT_2, std::f64::consts::FRAC_1_SQRT_2);
+    for _ in 0..3 {
+        let (x, y) = (a * tx, b * ty);
+        // Centre of curvature of the ellipse at (x, y)
+        let ex = (a * a - b * b) * tx.powi(3) / a;
+        let ey = (b * b - a * a) * ty.powi(3) / b;
+        let r = (x - ex).hypot(y - ey);
+        let q = (px - ex).hypot(py - ey);
+        tx = ((px - ex) * r / q + ex) / a;
+        ty = ((py - ey) * r / q + ey) / b;
+        tx = tx.clamp(0.0, 1.0);
+        ty = ty.clamp(0.0, 1.0);
+        let t = tx.hypot(ty);
+        tx /= t;
+        ty /= t;
+    }
+    (a * tx - px).powi(2) + (b * ty - py).powi(2)
+}
+
+/// Point where the line from the centre of a shape towards `target` leaves its outline
+pub(crate) fn clip_to_border(shape: NodeShape, rect: Rect, target: Point) -> Point {
+    let center = rect.center();
+    let direction = target - center;
+    // Direction as a fraction of the distance to the sides
+    let normalized = Vec2::new(direction.x / (rect.width() / 2.0), direction.y / (rect.height() / 2.0));
+    let scale = match shape {
+        NodeShape::Rectangle => 1.0 / normalized.x.abs().max(normalized.y.abs()),
+        NodeShape::Ellipse => 1.0 / normalized.hypot(),
+        NodeShape::Diamond => 1.0 / (normalized.x.abs() + normalized.y.abs()),
+        // The last crossing is used so that edges don't stop in the dips of a cloud
+        _ => polygon_edges(&polygon(shape, rect))
+            .filter_map(|(a, b)| ray_crossing(center, direction, a, b))
+            .fold(f64::NEG_INFINITY, f64::max),
+    };
+    if scale.is_finite() { center + direction * scale } else { center }
+}
+
+/// How far along `direction` from `origin` the ray crosses the segment from `a` to `b`, if it does
+fn ray_crossing(origin: Point, direction: Vec2, a: Point, b: Point) -> Option<f64> {
+    let cross = |u: Vec2, v: Vec2| u.x * v.y - u.y * v.x;
+    let side = b - a;
+    let denominator = cross(direction, side);
+    if denominator == 0.0 {
+        return None;
+    }
+    let to_start = a - origin;
+    let scale = cross(to_start, side) / denominator;
+    let along_side = cross(to_start, direction) / denominator;
+    (scale >= 0.0 && (0.0..=1.0).contains(&along_side)).then_some(scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect { x0: 0.0, y0: 0.0, x1: 200.0, y1: 100.0 };
+
+    #[test]
+    fn corners_are_outside_rounded_shapes() {
+        let corner = Point::new(2.0, 2.0);
+        assert!(contains(NodeShape::Rectangle, RECT, corner));
+        for shape in [NodeShape::RoundedRect, NodeShape::Ellipse, NodeShape::Diamond, NodeShape::Pill,
+                      NodeShape::Hexagon, NodeShape::Cloud] {
+            assert!(contains(shape, RECT, RECT.center()), "{:?}", shape);
+            assert!(!contains(shape, RECT, corner), "{:?}", shape);
+            assert!(distance_2(shape, RECT, corner) > 0.0, "{:?}", shape);
+        }
+        assert!(!contains(NodeShape::Ellipse, RECT, Point::new(-1.0, 50.0)));
+    }
+
+    #[test]
+    fn distance_is_measured_to_the_outline() {
+        // The diamond's top right side runs from (100, 0) to (200, 50)
+        let distance = distance_2(NodeShape::Diamond, RECT, Point::new(200.0, 0.0)).sqrt();
+        assert!((distance - 100.0 / 5f64.sqrt()).abs() < 1e-9);
+        let distance = distance_2(NodeShape::Ellipse, RECT, Point::new(250.0, 50.0)).sqrt();
+        assert!((distance - 50.0).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn direct_measurements_match_the_flattened_outline() {
+        let points = (-5..=45).flat_map(|i| (-5..=25).map(move |j| Point::new(i as f64 * 5.0 + 0.5, j as f64 * 5.0 + 0.5)));
+        for shape in [NodeShape::Rectangle, NodeShape::RoundedRect, NodeShape::Ellipse, NodeShape::Diamond, NodeShape::Pill] {
+            let outline = polygon(shape, RECT);
+            for point in points.clone() {
+                let inside_outline = RECT.contains(point) && polygon_contains(&outline, point);
+                let outline_distance = if inside_outline { 0.0 } else { polygon_distance_2(&outline, point).sqrt() };
+                let distance = distance_2(shape, RECT, point).sqrt();
+                assert!((distance - outline_distance).abs() <= TOLERANCE, "{:?} at {:?}", shape, point);
+                // Points right by the outline can be on either side of the flattened one
+                if polygon_distance_2(&outline, point).sqrt() > TOLERANCE {
+                    assert_eq!(contains(shape, RECT, point), inside_outline, "{:?} at {:?}", shape, point);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edges_are_clipped_to_the_outline() {
+        let target = Point::new(300.0, 150.0);
+        assert_eq!(clip_to_border(NodeShape::Rectangle, RECT, target), Point::new(200.0, 100.0));
+        let on_ellipse = clip_to_border(NodeShape::Ellipse, RECT, target);
+        let normalized = (on_ellipse - RECT.center()) / 100.0;
+        assert!((normalized.x.powi(2) + (normalized.y * 2.0).powi(2) - 1.0).abs() < 0.01);
+        let on_diamond = clip_to_border(NodeShape::Diamond, RECT, target);
+        assert!((on_diamond - Point::new(150.0, 75.0)).hypot() < 1e-9);
+        let on_cloud = clip_to_border(NodeShape::Cloud, RECT, Point::new(300.0, 50.0));
+        assert!(on_cloud.x > 190.0 && on_cloud.x <= 200.0);
+    }
+}
@@ -1,9 +1,53 @@
-use druid::Point;
+use druid::{Point, Rect, Vec2};
+use druid::kurbo::Line;
 use druid::MouseButtons;
 
+use crate::graph::node::Node;
+use crate::graph_view::guides::Guides;
+use crate::graph_view::marquee::Marquee;
+use crate::graph_view::resize::Handle;
+
 pub struct DragState {
     pub(crate) buttons: MouseButtons,
     pub(crate) last_mouse_pos: Point,
     pub(crate) has_moved: bool,
-    pub(crate) has_target: bool
-}
\ No newline at end of file
+    pub(crate) has_target: bool,
+    /// Drag started inside the node being edited, so it selects text instead of moving nodes
+    pub(crate) is_text_selection: bool,
+    /// Nodes being dragged as they were when the drag started. Only their rects end up in the undo step.
+    pub(crate) nodes_before: Vec<Node>,
+    /// How far the mouse has moved since the drag started, in scene units
+    pub(crate) mouse_offset: Vec2,
+    /// How far the dragged nodes have moved, which is `mouse_offset` adjusted for snapping. They are
+    /// only painted at their new position until the drag ends, when the move is applied to the graph in one go.
+    pub(crate) offset: Vec2,
+    /// Rect of the node that was grabbed, which is the one snapped to the grid while the rest of the selection follows
+    pub(crate) snap_anchor: Option<Rect>,
+    /// Nearby nodes the dragged nodes can line up with, found when they first move
+    pub(crate) guides: Option<Guides>,
+    /// Guide lines to show where the dragged nodes currently line up with others, in scene coordinates
+    pub(crate) guide_lines: Vec<Line>,
+    pub(crate) marquee: Option<Marquee>,
+    /// Handle being dragged to resize the only node in `nodes_before`. Unlike a move, the resize is
+    /// applied to the graph as the mouse moves so that text reflows and edges follow the new size.
+    pub(crate) resize_handle: Option<Handle>,
+}
+impl DragState {
+    pub(crate) fn new(buttons: MouseButtons, mouse_pos: Point) -> Self {
+        DragState {
+            buttons,
+            last_mouse_pos: mouse_pos,
+            has_moved: false,
+            has_target: false,
+            is_text_selection: false,
+            nodes_before: vec![],
+            mouse_offset: Vec2::ZERO,
+            offset: Vec2::ZERO,
+            snap_anchor: None,
+            guides: None,
+            guide_lines: vec![],
+            marquee: None,
+            resize_handle: None,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use druid::{Point, Rect, Size};
 use uuid::Uuid;
-use crate::graph::edge::{Edge, EdgeId};
+use crate::graph::edge::{Edge, EdgeType};
 use crate::graph::Graph;
 use crate::graph::node::{Node, NodeId};
 use crate::graph_view::display_graph::DisplayGraph;
@@ -17,117 +17,75 @@ pub(crate) fn arborealis_graph() -> DisplayGraph {
     DisplayGraph::from(&Graph {
         nodes: vec![
             Node {
-                id: NodeId(Uuid::new_v4()),
                 text: String::from("ARBOREALIS"),
                 rect: Rect::from_origin_size(Point::new(896.7, 170.2), Size::new(197.0, 75.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: sector_9_id,
                 text: String::from("sector9"),
                 rect: Rect::from_origin_size(Point::new(1327.7, 171.5), Size::new(132.0, 66.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: sapling_id,
                 text: String::from("sapling (based on druid)"),
                 rect: Rect::from_origin_size(Point::new(1592.5, 338.5), Size::new(179.0, 100.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: tree_rs_id,
                 text: String::from("tree-rs"),
                 rect: Rect::from_origin_size(Point::new(1288.0, 340.4), Size::new(217.0, 58.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
-                id: NodeId(Uuid::new_v4()),
                 text: String::from("tree-js (abandon?)"),
                 rect: Rect::from_origin_size(Point::new(927.5, 348.9), Size::new(237.0, 55.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: seed_rs_id,
                 text: String::from("seed-rs"),
                 rect: Rect::from_origin_size(Point::new(1269.2, 462.9), Size::new(231.0, 58.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: leaf_id,
                 text: String::from("leaf"),
                 rect: Rect::from_origin_size(Point::new(867.0, 466.9), Size::new(126.0, 160.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: root_id,
                 text: String::from("root"),
                 rect: Rect::from_origin_size(Point::new(1080.0, 485.7), Size::new(100.0, 60.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: arboretum_id,
                 text: String::from("arboretum"),
                 rect: Rect::from_origin_size(Point::new(1595.6, 580.4), Size::new(168.0, 58.0)),
+                ..Node::new(Point::ZERO, None)
             },
             Node {
                 id: automerge_id,
                 text: String::from("automerge"),
                 rect: Rect::from_origin_size(Point::new(1190.0, 707.9), Size::new(176.0, 79.0)),
+                ..Node::new(Point::ZERO, None)
             },
         ],
         edges: vec![
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: leaf_id,
-                to_node_id: root_id,
-                // edge_type: EdgeType::Bidirectional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: tree_rs_id,
-                to_node_id: sapling_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: arboretum_id,
-                to_node_id: sapling_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: sector_9_id,
-                to_node_id: tree_rs_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: tree_rs_id,
-                to_node_id: seed_rs_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: seed_rs_id,
-                to_node_id: root_id,
-                // edge_type: EdgeType::Bidirectional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: arboretum_id,
-                to_node_id: seed_rs_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: seed_rs_id,
-                to_node_id: automerge_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: root_id,
-                to_node_id: automerge_id,
-                // edge_type: EdgeType::Directional
-            },
-            Edge {
-                id: EdgeId(Uuid::new_v4()),
-                from_node_id: leaf_id,
-                to_node_id: automerge_id,
-                // edge_type: EdgeType::Directional
-            }
+            Edge::new(leaf_id, root_id, EdgeType::Bidirectional),
+            Edge::new(tree_rs_id, sapling_id, EdgeType::Directional),
+            Edge::new(arboretum_id, sapling_id, EdgeType::Directional),
+            Edge::new(sector_9_id, tree_rs_id, EdgeType::Directional),
+            Edge::new(tree_rs_id, seed_rs_id, EdgeType::Directional),
+            Edge::new(seed_rs_id, root_id, EdgeType::Bidirectional),
+            Edge::new(arboretum_id, seed_rs_id, EdgeType::Directional),
+            Edge::new(seed_rs_id, automerge_id, EdgeType::Directional),
+            Edge::new(root_id, automerge_id, EdgeType::Directional),
+            Edge::new(leaf_id, automerge_id, EdgeType::Directional)
         ]
     })
 }
\ No newline at end of file
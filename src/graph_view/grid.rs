@@ -0,0 +1,69 @@
+use druid::{Point, Rect, Vec2};
+
+/// Distance between the dots of the background grid, which nodes snap to, in scene units.
+/// Grid points sit on every multiple of this, so one is always at the scene origin.
+pub(crate) const GRID_SPACING: f64 = 36.0;
+
+/// Which point of a node is moved onto the grid
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub(crate) enum SnapTarget {
+    #[default]
+    Origin,
+    Center,
+}
+
+#[derive(Default)]
+pub(crate) struct GridSnap {
+    pub(crate) enabled: bool,
+    pub(crate) target: SnapTarget,
+}
+
+impl GridSnap {
+    /// Translation that puts a rect's snap target on the nearest grid point - zero when snapping is off
+    pub(crate) fn offset(&self, rect: Rect) -> Vec2 {
+        if !self.enabled {
+            return Vec2::ZERO;
+        }
+        let point = match self.target {
+            SnapTarget::Origin => rect.origin(),
+            SnapTarget::Center => rect.center(),
+        };
+        nearest_grid_point(point) - point
+    }
+}
+
+pub(crate) fn nearest_grid_point(point: Point) -> Point {
+    Point::new((point.x / GRID_SPACING).round() * GRID_SPACING, (point.y / GRID_SPACING).round() * GRID_SPACING)
+}
+
+/// Grid lines from the last one at or before `from` up to `to`, along one axis
+pub(crate) fn grid_lines(from: f64, to: f64) -> impl Iterator<Item = f64> {
+    let first = (from / GRID_SPACING).floor() as i64;
+    let last = (to / GRID_SPACING).ceil() as i64;
+    (first..=last).map(|i| i as f64 * GRID_SPACING)
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Size;
+
+    use super::*;
+
+    #[test]
+    fn rects_snap_by_origin_or_center() {
+        let rect = Rect::from_origin_size((40.0, -20.0), Size::new(100.0, 60.0));
+        let mut snap = GridSnap::default();
+        assert_eq!(snap.offset(rect), Vec2::ZERO);
+
+        snap.enabled = true;
+        assert_eq!((rect + snap.offset(rect)).origin(), Point::new(36.0, -36.0));
+        snap.target = SnapTarget::Center;
+        assert_eq!((rect + snap.offset(rect)).center(), Point::new(108.0, 0.0));
+    }
+
+    #[test]
+    fn grid_lines_cover_the_range() {
+        assert_eq!(grid_lines(-40.0, 40.0).collect::<Vec<_>>(), vec![-72.0, -36.0, 0.0, 36.0, 72.0]);
+        assert_eq!(grid_lines(0.0, 36.0).collect::<Vec<_>>(), vec![0.0, 36.0]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use druid::kurbo::Line;
+use druid::Rect;
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+
+/// How far around the dragged nodes to look for nodes to line up with, in scene units
+const GUIDE_RANGE: f64 = 800.0;
+/// Differences smaller than this are rounding error, so the edges still count as lined up
+const EPSILON: f64 = 1e-6;
+
+/// Smart guides for nodes being dragged: lines up their bounds' edges and centre lines with those
+/// of nearby nodes, and shows a guide line wherever they match
+pub(crate) struct Guides {
+    /// Bounds of the nodes near where the drag started, other than those being dragged
+    candidates: Vec<Rect>,
+}
+
+impl Guides {
+    pub(crate) fn new(graph: &DisplayGraph, dragged_bounds: Rect, dragged: &HashSet<NodeId>) -> Self {
+        let candidates = graph.nodes_intersecting(dragged_bounds.inset(GUIDE_RANGE)).into_iter()
+            .filter(|n| !dragged.contains(&n.id))
+            .map(|n| n.rect)
+            .collect();
+        Guides { candidates }
+    }
+
+    /// The smallest move along each axis, no larger than `max_distance`, that lines `bounds` up with
+    /// a nearby node
+    pub(crate) fn snap(&self, bounds: Rect, max_distance: f64) -> (Option<f64>, Option<f64>) {
+        let closest = |lines: fn(&Rect) -> [f64; 3]| {
+            let moving = lines(&bounds);
+            self.candidates.iter()
+                .flat_map(lines)
+                .flat_map(|target| moving.map(|m| target - m))
+                .filter(|delta| delta.abs() <= max_distance)
+                .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+        };
+        (closest(vertical_lines), closest(horizontal_lines))
+    }
+
+    /// Guide lines through every edge or centre line `bounds` shares with a nearby node,
+    /// spanning both of them
+    pub(crate) fn lines(&self, bounds: Rect) -> Vec<Line> {
+        let mut lines = vec![];
+        for candidate in &self.candidates {
+            for x in vertical_lines(&bounds) {
+                if vertical_lines(candidate).iter().any(|cx| (cx - x).abs() < EPSILON) {
+                    lines.push(Line::new((x, bounds.y0.min(candidate.y0)), (x, bounds.y1.max(candidate.y1))));
+                }
+            }
+            for y in horizontal_lines(&bounds) {
+                if horizontal_lines(candidate).iter().any(|cy| (cy - y).abs() < EPSILON) {
+                    lines.push(Line::new((bounds.x0.min(candidate.x0), y), (bounds.x1.max(candidate.x1), y)));
+                }
+            }
+        }
+        lines
+    }
+}
+
+fn vertical_lines(rect: &Rect) -> [f64; 3] {
+    [rect.x0, rect.center().x, rect.x1]
+}
+
+fn horizontal_lines(rect: &Rect) -> [f64; 3] {
+    [rect.y0, rect.center().y, rect.y1]
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Point, Size, Vec2};
+
+    use crate::graph::Graph;
+    use crate::graph::node::Node;
+
+    use super::*;
+
+    fn guides_for(nodes: Vec<Node>, dragged: &Node) -> Guides {
+        let display_graph = DisplayGraph::from(&Graph { nodes, edges: vec![] });
+        Guides::new(&display_graph, dragged.rect, &HashSet::from([dragged.id]))
+    }
+
+    #[test]
+    fn dragged_nodes_snap_to_the_nearest_matching_line() {
+        let target = Node::new(Point::new(0.0, 0.0), Some(Size::new(100.0, 60.0)));
+        let dragged = Node::new(Point::new(54.0, 200.0), Some(Size::new(40.0, 40.0)));
+        let guides = guides_for(vec![target, dragged.clone()], &dragged);
+
+        // The dragged node's centre (54) is 4 from the target's right edge (50)
+        assert_eq!(guides.snap(dragged.rect, 5.0), (Some(-4.0), None));
+        assert_eq!(guides.snap(dragged.rect, 3.0), (None, None));
+        // Nodes being dragged are never lined up with themselves
+        assert_eq!(guides_for(vec![dragged.clone()], &dragged).snap(dragged.rect, 5.0), (None, None));
+    }
+
+    #[test]
+    fn guide_lines_span_both_nodes() {
+        let target = Node::new(Point::new(0.0, 0.0), Some(Size::new(100.0, 60.0)));
+        let dragged = Node::new(Point::new(0.0, 200.0), Some(Size::new(40.0, 40.0)));
+        let guides = guides_for(vec![target, dragged.clone()], &dragged);
+
+        // Only the centre lines match
+        assert_eq!(guides.lines(dragged.rect), vec![Line::new((0.0, -30.0), (0.0, 220.0))]);
+        assert!(guides.lines(dragged.rect + Vec2::new(1.0, 0.0)).is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
+
+use crate::graph::edge::{Edge, EdgeId};
+use crate::graph::node::{Node, NodeId};
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::element_id::ElementId;
+
+/// Maximum number of undoable steps kept - the oldest steps are dropped first
+const MAX_UNDO_STEPS: usize = 200;
+
+/// A single reversible change to a [`DisplayGraph`]. Added and removed elements record their `index`
+/// in the graph's order, so undoing a removal puts them back where they were.
+#[derive(Clone)]
+pub(crate) enum GraphEdit {
+    AddNode { node: Node, index: usize },
+    RemoveNode { node: Node, index: usize },
+    AddEdge { edge: Edge, index: usize },
+    RemoveEdge { edge: Edge, index: usize },
+    UpdateNode { before: Node, after: Node },
+    UpdateEdge { before: Edge, after: Edge },
+}
+
+impl GraphEdit {
+    fn apply(&self, graph: &mut DisplayGraph) {
+        match self {
+            GraphEdit::AddNode { node, index } => graph.insert_node(*index, node.clone()),
+            GraphEdit::RemoveNode { node, .. } => { graph.remove_node(&node.id); }
+            GraphEdit::AddEdge { edge, index } => graph.insert_edge(*index, edge.clone()),
+            GraphEdit::RemoveEdge { edge, .. } => { graph.remove_edge(&edge.id); }
+            GraphEdit::UpdateNode { after, .. } => graph.update_node(after.clone()),
+            GraphEdit::UpdateEdge { after, .. } => graph.update_edge(after.clone()),
+        }
+    }
+
+    fn inverse(&self) -> GraphEdit {
+        match self {
+            GraphEdit::AddNode { node, index } => GraphEdit::RemoveNode { node: node.clone(), index: *index },
+            GraphEdit::RemoveNode { node, index } => GraphEdit::AddNode { node: node.clone(), index: *index },
+            GraphEdit::AddEdge { edge, index } => GraphEdit::RemoveEdge { edge: edge.clone(), index: *index },
+            GraphEdit::RemoveEdge { edge, index } => GraphEdit::AddEdge { edge: edge.clone(), index: *index },
+            GraphEdit::UpdateNode { before, after } =>
+                GraphEdit::UpdateNode { before: after.clone(), after: before.clone() },
+            GraphEdit::UpdateEdge { before, after } =>
+                GraphEdit::UpdateEdge { before: after.clone(), after: before.clone() },
+        }
+    }
+}
+
+/// Transaction that deletes the given elements, along with every edge connected to a deleted node
+pub(crate) fn removal(graph: &DisplayGraph, element_ids: impl IntoIterator<Item=ElementId>) -> Vec<GraphEdit> {
+    let mut edge_ids: HashSet<EdgeId> = HashSet::new();
+    let mut node_ids: Vec<NodeId> = vec![];
+    for element_id in element_ids {
+        match element_id {
+            ElementId::Node(node_id) => {
+                edge_ids.extend(graph.node_edge_ids(&node_id));
+                node_ids.push(node_id);
+            }
+            ElementId::Edge(edge_id) => { edge_ids.insert(edge_id); }
+        }
+    }
+    // Edges are removed before their nodes so that undoing restores the nodes first. Each kind is removed
+    // from the back of the graph's order, so the index recorded for each element is still its index when
+    // it's removed, and undoing puts everything back in its original place.
+    let mut edges: Vec<(usize, Edge)> = edge_ids.iter()
+        .filter_map(|edge_id| Some((graph.edge_index(edge_id)?, Edge::from(graph.get_edge(edge_id)?))))
+        .collect();
+    edges.sort_unstable_by_key(|(index, _)| Reverse(*index));
+    let mut nodes: Vec<(usize, Node)> = node_ids.iter()
+        .filter_map(|node_id| Some((graph.node_index(node_id)?, Node::from(graph.get_node(node_id)?))))
+        .collect();
+    nodes.sort_unstable_by_key(|(index, _)| Reverse(*index));
+    edges.into_iter().map(|(index, edge)| GraphEdit::RemoveEdge { edge, index })
+        .chain(nodes.into_iter().map(|(index, node)| GraphEdit::RemoveNode { node, index }))
+        .collect()
+}
+
+/// Undo and redo stacks of transactions, where each transaction is a group of edits that is undone
+/// and redone as one step. Edits within a transaction are applied in order and undone in reverse.
+#[derive(Default)]
+pub(crate) struct History {
+    undo_stack: VecDeque<Vec<GraphEdit>>,
+    redo_stack: Vec<Vec<GraphEdit>>,
+}
+
+impl History {
+    /// Applies a transaction to the graph and records it as one undoable step
+    pub(crate) fn apply(&mut self, graph: &mut DisplayGraph, transaction: Vec<GraphEdit>) {
+        for edit in &transaction {
+            edit.apply(graph);
+        }
+        self.record(transaction);
+    }
+
+    /// Records a transaction that has already been applied to the graph, such as a finished drag
+    pub(crate) fn record(&mut self, transaction: Vec<GraphEdit>) {
+        if transaction.is_empty() {
+            return;
+        }
+        if self.undo_stack.len() == MAX_UNDO_STEPS {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(transaction);
+        self.redo_stack.clear();
+    }
+
+    /// Applies follow-up edits as part of the most recent transaction, so they're undone along with it
+    pub(crate) fn amend(&mut self, graph: &mut DisplayGraph, edits: Vec<GraphEdit>) {
+        for edit in &edits {
+            edit.apply(graph);
+        }
+        match self.undo_stack.back_mut() {
+            Some(transaction) => transaction.extend(edits),
+            None => self.record(edits),
+        }
+    }
+
+    /// Returns false if there was nothing to undo
+    pub(crate) fn undo(&mut self, graph: &mut DisplayGraph) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(transaction) => {
+                for edit in transaction.iter().rev() {
+                    edit.inverse().apply(graph);
+                }
+                self.redo_stack.push(transaction);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns false if there was nothing to redo
+    pub(crate) fn redo(&mut self, graph: &mut DisplayGraph) -> bool {
+        match self.redo_stack.pop() {
+            Some(transaction) => {
+                for edit in &transaction {
+                    edit.apply(graph);
+                }
+                self.undo_stack.push_back(transaction);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Point;
+
+    use crate::graph::edge::EdgeType;
+    use crate::graph::Graph;
+
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_restore_removed_nodes_and_edges() {
+        let from = Node::new(Point::new(0.0, 0.0), None);
+        let to = Node::new(Point::new(200.0, 0.0), None);
+        let edge = Edge::new(from.id, to.id, EdgeType::Directional);
+        let original = Graph { nodes: vec![from.clone(), to], edges: vec![edge.clone()] };
+        let mut display_graph = DisplayGraph::from(&original);
+        let mut history = History::default();
+
+        history.apply(&mut display_graph, vec![GraphEdit::RemoveEdge { edge, index: 0 }, GraphEdit::RemoveNode { node: from, index: 0 }]);
+        assert_eq!(Graph::from(&display_graph).nodes.len(), 1);
+        assert!(history.undo(&mut display_graph));
+        assert_eq!(Graph::from(&display_graph).edges, original.edges);
+        assert_eq!(Graph::from(&display_graph).nodes.len(), 2);
+        assert!(history.redo(&mut display_graph));
+        assert!(Graph::from(&display_graph).edges.is_empty());
+        assert!(!history.redo(&mut display_graph));
+    }
+
+    #[test]
+    fn undoing_a_removal_restores_the_original_order() {
+        let nodes: Vec<Node> = (0..4).map(|i| Node::new(Point::new(i as f64 * 200.0, 0.0), None)).collect();
+        let edges = vec![
+            Edge::new(nodes[0].id, nodes[1].id, EdgeType::Directional),
+            Edge::new(nodes[1].id, nodes[2].id, EdgeType::Directional),
+            Edge::new(nodes[2].id, nodes[3].id, EdgeType::Directional),
+            Edge::new(nodes[3].id, nodes[0].id, EdgeType::Directional),
+        ];
+        let original = Graph { nodes: nodes.clone(), edges: edges.clone() };
+        let mut display_graph = DisplayGraph::from(&original);
+        let mut history = History::default();
+
+        let transaction = removal(&display_graph, [ElementId::Node(nodes[1].id), ElementId::Edge(edges[2].id)]);
+        history.apply(&mut display_graph, transaction);
+        assert_eq!(Graph::from(&display_graph).nodes.len(), 3);
+        assert_eq!(Graph::from(&display_graph).edges, vec![edges[3].clone()]);
+        assert!(history.undo(&mut display_graph));
+        assert_eq!(Graph::from(&display_graph), original);
+    }
+
+    #[test]
+    fn amended_edits_are_undone_with_the_last_transaction() {
+        let mut display_graph = DisplayGraph::default();
+        let mut history = History::default();
+        let existing = Node::new(Point::new(0.0, 0.0), None);
+        history.apply(&mut display_graph, vec![GraphEdit::AddNode { node: existing.clone(), index: 0 }]);
+        history.apply(&mut display_graph, vec![GraphEdit::AddNode { node: Node::new(Point::new(10.0, 0.0), None), index: 1 }]);
+        let moved = Node { rect: existing.rect.with_origin((-200.0, 0.0)), ..existing.clone() };
+        history.amend(&mut display_graph, vec![GraphEdit::UpdateNode { before: existing.clone(), after: moved }]);
+
+        assert!(history.undo(&mut display_graph));
+        assert_eq!(Graph::from(&display_graph).nodes, vec![existing]);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut display_graph = DisplayGraph::default();
+        let mut history = History::default();
+        for i in 0..MAX_UNDO_STEPS + 10 {
+            let node = Node::new(Point::new(i as f64 * 200.0, 0.0), None);
+            history.apply(&mut display_graph, vec![GraphEdit::AddNode { node, index: i }]);
+        }
+        let mut undone_steps = 0;
+        while history.undo(&mut display_graph) {
+            undone_steps += 1;
+        }
+        assert_eq!(undone_steps, MAX_UNDO_STEPS);
+        assert_eq!(Graph::from(&display_graph).nodes.len(), 10);
+    }
+}
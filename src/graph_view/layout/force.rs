@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use druid::{Rect, Vec2};
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::layout::{Layout, NODE_SPACING, radius};
+
+/// Preferred space between the borders of two connected nodes, in scene units
+const SPRING_LENGTH: f64 = NODE_SPACING * 2.0;
+/// Nodes further apart than this, border to border, don't push each other away
+const REPULSION_RANGE: f64 = SPRING_LENGTH * 3.0;
+/// Furthest a node can move in the first step - this shrinks every step so the layout settles
+const INITIAL_TEMPERATURE: f64 = 60.0;
+const COOLING_RATE: f64 = 0.95;
+/// Below this, nodes are moving too little for further steps to make a visible difference
+const MIN_MOVEMENT: f64 = 0.5;
+/// Upper bound on steps when running to convergence, in case the layout oscillates
+pub(crate) const MAX_STEPS: usize = 500;
+
+/// Spring-embedder layout (Fruchterman-Reingold) where edges pull their nodes together and nodes
+/// push away from any others nearby. Distances are measured between node borders rather than
+/// centres, so large nodes get more room. Each step reads the positions currently in the graph
+/// and uses its R-tree to find the nodes close enough to repel each other.
+pub(crate) struct ForceLayout {
+    temperature: f64,
+    last_movement: f64,
+}
+
+impl ForceLayout {
+    pub(crate) fn new() -> Self {
+        ForceLayout { temperature: INITIAL_TEMPERATURE, last_movement: f64::INFINITY }
+    }
+
+    /// True once further steps would barely move anything
+    pub(crate) fn is_settled(&self) -> bool {
+        self.temperature < MIN_MOVEMENT || self.last_movement < MIN_MOVEMENT
+    }
+
+    /// Works out where every unpinned node moves to in one step of the simulation
+    pub(crate) fn step(&mut self, graph: &DisplayGraph, pinned: &HashSet<NodeId>) -> Layout {
+        let nodes = graph.nodes();
+        let mut layout = Layout::new();
+        self.last_movement = 0.0;
+        for node in nodes.iter().filter(|n| !pinned.contains(&n.id)) {
+            let mut force = Vec2::ZERO;
+            let center = node.rect.center();
+            for other in graph.nodes_within_distance(center, radius(node.rect) + REPULSION_RANGE) {
+                if other.id != node.id {
+                    let gap = border_gap(node.rect, other.rect).max(1.0);
+                    force += direction(node.id, node.rect, other.id, other.rect) * SPRING_LENGTH * SPRING_LENGTH / gap;
+                }
+            }
+            for edge_id in graph.node_edge_ids(&node.id) {
+                let edge = graph.get_edge(edge_id).unwrap();
+                let other_id = if edge.from_node == node.id { edge.to_node } else { edge.from_node };
+                if other_id == node.id {
+                    continue;
+                }
+                let other = graph.get_node(&other_id).unwrap();
+                // Overlapping nodes have a negative gap, so the spring pushes them apart instead
+                let gap = border_gap(node.rect, other.rect);
+                force -= direction(node.id, node.rect, other.id, other.rect) * gap * gap.abs() / SPRING_LENGTH;
+            }
+            let magnitude = force.hypot();
+            if magnitude > 0.0 {
+                let movement = magnitude.min(self.temperature);
+                self.last_movement = self.last_movement.max(movement);
+                layout.insert(node.id, center + force / magnitude * movement);
+            }
+        }
+        self.temperature *= COOLING_RATE;
+        layout
+    }
+}
+
+fn border_gap(a: Rect, b: Rect) -> f64 {
+    (a.center() - b.center()).hypot() - radius(a) - radius(b)
+}
+
+/// Unit vector from `other` towards `node`. Nodes on top of each other are split apart horizontally,
+/// in opposite directions decided by their IDs.
+fn direction(node_id: NodeId, node: Rect, other_id: NodeId, other: Rect) -> Vec2 {
+    let delta = node.center() - other.center();
+    if delta.hypot() > 0.0 {
+        delta / delta.hypot()
+    } else if node_id.0 < other_id.0 {
+        Vec2::new(-1.0, 0.0)
+    } else {
+        Vec2::new(1.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Point, Size};
+
+    use crate::graph::edge::{Edge, EdgeType};
+    use crate::graph::Graph;
+    use crate::graph::node::Node;
+    use crate::graph_view::layout::apply_in_place;
+
+    use super::*;
+
+    fn run(display_graph: &mut DisplayGraph, pinned: &HashSet<NodeId>) {
+        let mut force_layout = ForceLayout::new();
+        for _ in 0..MAX_STEPS {
+            let layout = force_layout.step(display_graph, pinned);
+            apply_in_place(display_graph, &layout);
+            if force_layout.is_settled() {
+                return;
+            }
+        }
+        panic!("layout didn't settle");
+    }
+
+    fn center(display_graph: &DisplayGraph, node: &Node) -> Point {
+        display_graph.get_node(&node.id).unwrap().rect.center()
+    }
+
+    #[test]
+    fn connected_nodes_settle_about_a_spring_length_apart() {
+        let a = Node::new(Point::new(0.0, 0.0), Some(Size::new(100.0, 60.0)));
+        let b = Node::new(Point::new(1000.0, 300.0), Some(Size::new(100.0, 60.0)));
+        let graph = Graph { edges: vec![Edge::new(a.id, b.id, EdgeType::Directional)], nodes: vec![a.clone(), b.clone()] };
+        let mut display_graph = DisplayGraph::from(&graph);
+        run(&mut display_graph, &HashSet::new());
+
+        let gap = border_gap(display_graph.get_node(&a.id).unwrap().rect, display_graph.get_node(&b.id).unwrap().rect);
+        assert!((gap - SPRING_LENGTH).abs() < SPRING_LENGTH * 0.1, "gap was {}", gap);
+    }
+
+    #[test]
+    fn pinned_nodes_stay_put_and_overlaps_are_pushed_apart() {
+        let nodes: Vec<Node> = (0..3).map(|_| Node::new(Point::new(50.0, 50.0), None)).collect();
+        let edges = vec![Edge::new(nodes[0].id, nodes[1].id, EdgeType::Undirected)];
+        let mut display_graph = DisplayGraph::from(&Graph { nodes: nodes.clone(), edges });
+        let pinned = HashSet::from([nodes[0].id]);
+        run(&mut display_graph, &pinned);
+
+        assert_eq!(center(&display_graph, &nodes[0]), Point::new(50.0, 50.0));
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                let overlap = display_graph.get_node(&a.id).unwrap().rect.intersect(display_graph.get_node(&b.id).unwrap().rect);
+                assert!(overlap.area() == 0.0, "nodes still overlap");
+            }
+        }
+    }
+}
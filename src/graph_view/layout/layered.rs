@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use druid::{Point, Rect, Size, Vec2};
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::layout::{Layout, NODE_SPACING};
+
+/// Rounds of reordering layers to reduce edge crossings
+const CROSSING_SWEEPS: usize = 12;
+/// Rounds of moving nodes within their layer to line them up with their neighbours
+const ALIGNMENT_SWEEPS: usize = 8;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum LayerDirection {
+    /// Layers are rows, with edges pointing down
+    TopDown,
+    /// Layers are columns, with edges pointing right
+    LeftToRight,
+}
+
+/// A node in the layered graph - either one of the nodes being laid out, or a dummy standing in for
+/// the part of a long edge that passes through a layer, which keeps room for the edge.
+/// The nodes being laid out come first, at the same indices as in the list of nodes.
+struct Vertex {
+    rank: usize,
+    /// Size along the layer
+    breadth: f64,
+    upper: Vec<usize>,
+    lower: Vec<usize>,
+}
+
+/// Sugiyama-style layout of the given nodes, using only the edges between them. Nodes are ranked
+/// along edge direction with cycles broken by reversing edges, then each layer is ordered to reduce
+/// crossings and nodes are lined up with their neighbours. The result keeps the top left corner of
+/// the nodes' bounding box where it was.
+pub(crate) fn layered_layout(graph: &DisplayGraph, node_ids: &[NodeId], direction: LayerDirection) -> Layout {
+    if node_ids.is_empty() {
+        return Layout::new();
+    }
+    let rects: Vec<Rect> = node_ids.iter().map(|id| graph.get_node(id).unwrap().rect).collect();
+    // Work in (depth, breadth) coordinates and only map them to x and y at the end
+    let to_axes = |size: Size| match direction {
+        LayerDirection::TopDown => (size.height, size.width),
+        LayerDirection::LeftToRight => (size.width, size.height),
+    };
+    let breadth_of = |point: Point| match direction {
+        LayerDirection::TopDown => point.x,
+        LayerDirection::LeftToRight => point.y,
+    };
+
+    let indices: HashMap<NodeId, usize> = node_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut edges: Vec<(usize, usize)> = vec![];
+    for (from, node_id) in node_ids.iter().enumerate() {
+        for edge_id in graph.node_edge_ids(node_id) {
+            let edge = graph.get_edge(edge_id).unwrap();
+            if edge.from_node == *node_id && edge.to_node != *node_id {
+                if let Some(to) = indices.get(&edge.to_node) {
+                    edges.push((from, *to));
+                }
+            }
+        }
+    }
+    let edges = break_cycles(node_ids.len(), edges);
+    let ranks = longest_path_ranks(node_ids.len(), &edges);
+
+    let mut vertices: Vec<Vertex> = (0..node_ids.len()).map(|i| Vertex {
+        rank: ranks[i],
+        breadth: to_axes(rects[i].size()).1,
+        upper: vec![],
+        lower: vec![],
+    }).collect();
+    // Where each vertex currently sits along the layer, used for the initial order
+    let mut current_breadths: Vec<f64> = rects.iter().map(|r| breadth_of(r.center())).collect();
+    for (from, to) in edges {
+        let mut upper = from;
+        for rank in ranks[from] + 1..ranks[to] {
+            let t = (rank - ranks[from]) as f64 / (ranks[to] - ranks[from]) as f64;
+            current_breadths.push(current_breadths[from] + (current_breadths[to] - current_breadths[from]) * t);
+            vertices.push(Vertex { rank, breadth: 0.0, upper: vec![upper], lower: vec![] });
+            let dummy = vertices.len() - 1;
+            vertices[upper].lower.push(dummy);
+            upper = dummy;
+        }
+        vertices[upper].lower.push(to);
+        vertices[to].upper.push(upper);
+    }
+
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; ranks.iter().max().unwrap() + 1];
+    for (v, vertex) in vertices.iter().enumerate() {
+        layers[vertex.rank].push(v);
+    }
+    for layer in &mut layers {
+        layer.sort_by(|a, b| current_breadths[*a].total_cmp(&current_breadths[*b]));
+    }
+    let layers = reduce_crossings(&vertices, layers);
+    let breadths = align(&vertices, &layers);
+
+    // Every layer is as deep as its deepest node
+    let mut layer_depths = vec![0.0f64; layers.len()];
+    for (i, rect) in rects.iter().enumerate() {
+        layer_depths[ranks[i]] = layer_depths[ranks[i]].max(to_axes(rect.size()).0);
+    }
+    let mut layer_centers = Vec::with_capacity(layers.len());
+    let mut layer_start = 0.0;
+    for layer_depth in &layer_depths {
+        layer_centers.push(layer_start + layer_depth / 2.0);
+        layer_start += layer_depth + NODE_SPACING;
+    }
+
+    let centers: Vec<Point> = (0..node_ids.len()).map(|i| {
+        let (depth, breadth) = (layer_centers[ranks[i]], breadths[i]);
+        match direction {
+            LayerDirection::TopDown => Point::new(breadth, depth),
+            LayerDirection::LeftToRight => Point::new(depth, breadth),
+        }
+    }).collect();
+    let bounds_origin = |rects: &mut dyn Iterator<Item = Rect>| {
+        rects.fold(Point::new(f64::INFINITY, f64::INFINITY), |p, r| Point::new(p.x.min(r.x0), p.y.min(r.y0)))
+    };
+    let old_origin = bounds_origin(&mut rects.iter().copied());
+    let new_origin = bounds_origin(&mut centers.iter().zip(&rects).map(|(c, r)| Rect::from_center_size(*c, r.size())));
+    let offset: Vec2 = old_origin - new_origin;
+    node_ids.iter().zip(centers).map(|(id, center)| (*id, center + offset)).collect()
+}
+
+/// Reverses every edge that points back up the depth-first search tree, leaving an acyclic graph
+fn break_cycles(node_count: usize, edges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut outgoing: Vec<Vec<usize>> = vec![vec![]; node_count];
+    for (i, (from, _)) in edges.iter().enumerate() {
+        outgoing[*from].push(i);
+    }
+    const UNVISITED: u8 = 0;
+    const ON_STACK: u8 = 1;
+    const DONE: u8 = 2;
+    let mut states = vec![UNVISITED; node_count];
+    let mut reversed = vec![false; edges.len()];
+    for start in 0..node_count {
+        if states[start] != UNVISITED {
+            continue;
+        }
+        // Iterative so long chains can't overflow the stack - each entry is a node and its next edge
+        let mut stack = vec![(start, 0)];
+        states[start] = ON_STACK;
+        while let Some((node, next)) = stack.last_mut() {
+            match outgoing[*node].get(*next) {
+                Some(&edge) => {
+                    *next += 1;
+                    let to = edges[edge].1;
+                    match states[to] {
+                        UNVISITED => {
+                            states[to] = ON_STACK;
+                            stack.push((to, 0));
+                        }
+                        ON_STACK => reversed[edge] = true,
+                        _ => {}
+                    }
+                }
+                None => {
+                    states[*node] = DONE;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    edges.into_iter().zip(reversed).map(|((from, to), reversed)| if reversed { (to, from) } else { (from, to) }).collect()
+}
+
+/// Puts every node one rank below the lowest of the nodes with edges into it
+fn longest_path_ranks(node_count: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut in_degrees = vec![0; node_count];
+    let mut outgoing: Vec<Vec<usize>> = vec![vec![]; node_count];
+    for (from, to) in edges {
+        in_degrees[*to] += 1;
+        outgoing[*from].push(*to);
+    }
+    let mut ranks = vec![0; node_count];
+    let mut ready: Vec<usize> = (0..node_count).filter(|i| in_degrees[*i] == 0).collect();
+    while let Some(node) = ready.pop() {
+        for to in &outgoing[node] {
+            ranks[*to] = ranks[*to].max(ranks[node] + 1);
+            in_degrees[*to] -= 1;
+            if in_degrees[*to] == 0 {
+                ready.push(*to);
+            }
+        }
+    }
+    ranks
+}
+
+/// Reorders layers by the barycentre heuristic, sweeping down and up, and keeps the best order seen
+fn reduce_crossings(vertices: &[Vertex], mut layers: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    let mut positions = vec![0usize; vertices.len()];
+    let update_positions = |layer: &[usize], positions: &mut Vec<usize>| {
+        for (i, v) in layer.iter().enumerate() {
+            positions[*v] = i;
+        }
+    };
+    for layer in &layers {
+        update_positions(layer, &mut positions);
+    }
+    let mut best = (count_crossings(vertices, &layers, &positions), layers.clone());
+    for sweep in 0..CROSSING_SWEEPS {
+        let downwards = sweep % 2 == 0;
+        let order: Vec<usize> = if downwards { (1..layers.len()).collect() } else { (0..layers.len().saturating_sub(1)).rev().collect() };
+        for rank in order {
+            let barycenters: HashMap<usize, f64> = layers[rank].iter().map(|v| {
+                let neighbours = if downwards { &vertices[*v].upper } else { &vertices[*v].lower };
+                let barycenter = if neighbours.is_empty() {
+                    positions[*v] as f64
+                } else {
+                    neighbours.iter().map(|n| positions[*n] as f64).sum::<f64>() / neighbours.len() as f64
+                };
+                (*v, barycenter)
+            }).collect();
+            layers[rank].sort_by(|a, b| barycenters[a].total_cmp(&barycenters[b]));
+            update_positions(&layers[rank], &mut positions);
+        }
+        let crossings = count_crossings(vertices, &layers, &positions);
+        if crossings < best.0 {
+            best = (crossings, layers.clone());
+        }
+    }
+    best.1
+}
+
+fn count_crossings(vertices: &[Vertex], layers: &[Vec<usize>], positions: &[usize]) -> usize {
+    let mut crossings = 0;
+    for layer in layers {
+        let edges: Vec<(usize, usize)> = layer.iter()
+            .flat_map(|v| vertices[*v].lower.iter().map(|l| (positions[*v], positions[*l])))
+            .collect();
+        for (i, (upper_a, lower_a)) in edges.iter().enumerate() {
+            for (upper_b, lower_b) in &edges[i + 1..] {
+                if (upper_a < upper_b && lower_a > lower_b) || (upper_a > upper_b && lower_a < lower_b) {
+                    crossings += 1;
+                }
+            }
+        }
+    }
+    crossings
+}
+
+/// Positions along each layer, keeping the layer's order and spacing while moving each vertex
+/// towards the average of its neighbours in the layer above, then below, and so on
+fn align(vertices: &[Vertex], layers: &[Vec<usize>]) -> Vec<f64> {
+    let separation = |a: usize, b: usize| (vertices[a].breadth + vertices[b].breadth) / 2.0 + NODE_SPACING;
+    let mut breadths = vec![0.0; vertices.len()];
+    for layer in layers {
+        let mut cursor = 0.0;
+        for (i, v) in layer.iter().enumerate() {
+            if i > 0 {
+                cursor += separation(layer[i - 1], *v);
+            }
+            breadths[*v] = cursor;
+        }
+        // Centre every layer on the same line to start with
+        for v in layer {
+            breadths[*v] -= cursor / 2.0;
+        }
+    }
+    for sweep in 0..ALIGNMENT_SWEEPS {
+        let downwards = sweep % 2 == 0;
+        for layer in layers {
+            let desired: Vec<f64> = layer.iter().map(|v| {
+                let neighbours = if downwards { &vertices[*v].upper } else { &vertices[*v].lower };
+                if neighbours.is_empty() {
+                    breadths[*v]
+                } else {
+                    neighbours.iter().map(|n| breadths[*n]).sum::<f64>() / neighbours.len() as f64
+                }
+            }).collect();
+            // Packing towards each end gives two placements that both keep the spacing,
+            // so their average does too
+            let mut from_start = desired.clone();
+            for i in 1..layer.len() {
+                from_start[i] = from_start[i].max(from_start[i - 1] + separation(layer[i - 1], layer[i]));
+            }
+            let mut from_end = desired;
+            for i in (0..layer.len().saturating_sub(1)).rev() {
+                from_end[i] = from_end[i].min(from_end[i + 1] - separation(layer[i], layer[i + 1]));
+            }
+            for (i, v) in layer.iter().enumerate() {
+                breadths[*v] = (from_start[i] + from_end[i]) / 2.0;
+            }
+        }
+    }
+    breadths
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Graph;
+    use crate::graph_view::test_util::{edge, node};
+
+    use super::*;
+
+    const NODE_SIZE: (f64, f64) = (100.0, 60.0);
+
+    fn layout_all(graph: &Graph, direction: LayerDirection) -> Layout {
+        let node_ids: Vec<NodeId> = graph.nodes.iter().map(|n| n.id).collect();
+        layered_layout(&DisplayGraph::from(graph), &node_ids, direction)
+    }
+
+    #[test]
+    fn nodes_are_ranked_along_edges_and_long_edges_keep_their_own_column() {
+        let (a, b, c) = (node((0.0, 0.0), NODE_SIZE), node((0.0, 100.0), NODE_SIZE), node((0.0, 200.0), NODE_SIZE));
+        let graph = Graph { edges: vec![edge(&a, &b), edge(&b, &c), edge(&a, &c)], nodes: vec![a.clone(), b.clone(), c.clone()] };
+        let layout = layout_all(&graph, LayerDirection::TopDown);
+
+        assert_eq!(layout[&b.id].y, layout[&a.id].y + 60.0 + NODE_SPACING);
+        assert_eq!(layout[&c.id].y, layout[&b.id].y + 60.0 + NODE_SPACING);
+        // The dummy for a -> c sits beside b, so b is pushed to one side of the straight line from a to c
+        assert!((layout[&b.id].x - layout[&a.id].x).abs() > 0.0);
+        // The top left of the nodes' bounds stays where it was
+        let min_x = layout.values().map(|c| c.x - 50.0).fold(f64::INFINITY, f64::min);
+        assert_eq!((min_x, layout[&a.id].y - 30.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        let (a, b, c) = (node((0.0, 0.0), NODE_SIZE), node((200.0, 0.0), NODE_SIZE), node((400.0, 0.0), NODE_SIZE));
+        let graph = Graph { edges: vec![edge(&a, &b), edge(&b, &c), edge(&c, &a)], nodes: vec![a.clone(), b.clone(), c.clone()] };
+        let layout = layout_all(&graph, LayerDirection::LeftToRight);
+
+        assert!(layout[&a.id].x < layout[&b.id].x);
+        assert!(layout[&b.id].x < layout[&c.id].x);
+    }
+
+    #[test]
+    fn layers_are_reordered_to_remove_crossings() {
+        let (s1, s2) = (node((0.0, 0.0), NODE_SIZE), node((200.0, 0.0), NODE_SIZE));
+        let (t1, t2) = (node((0.0, 200.0), NODE_SIZE), node((200.0, 200.0), NODE_SIZE));
+        let graph = Graph {
+            edges: vec![edge(&s1, &t2), edge(&s2, &t1)],
+            nodes: vec![s1.clone(), s2.clone(), t1.clone(), t2.clone()],
+        };
+        let layout = layout_all(&graph, LayerDirection::TopDown);
+
+        assert_eq!(layout[&s1.id].x < layout[&s2.id].x, layout[&t2.id].x < layout[&t1.id].x);
+    }
+
+    #[test]
+    fn only_the_given_nodes_are_moved() {
+        let (a, b, outside) = (node((0.0, 0.0), NODE_SIZE), node((300.0, 300.0), NODE_SIZE), node((0.0, 100.0), NODE_SIZE));
+        let graph = Graph { edges: vec![edge(&a, &outside), edge(&outside, &b)], nodes: vec![a.clone(), b.clone(), outside.clone()] };
+        let layout = layered_layout(&DisplayGraph::from(&graph), &[a.id, b.id], LayerDirection::TopDown);
+
+        assert_eq!(layout.len(), 2);
+        // Without the outside node, a and b aren't connected and share the first layer
+        assert_eq!(layout[&a.id].y, layout[&b.id].y);
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use druid::{Point, Rect};
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+
+pub(crate) mod force;
+pub(crate) mod layered;
+pub(crate) mod overlap;
+pub(crate) mod radial;
+pub(crate) mod tree;
+
+/// Horizontal and vertical space left between nodes placed by a layout, in scene units
+const NODE_SPACING: f64 = 40.0;
+
+/// New centre points for the nodes a layout moved. Layouts only read the graph, so the view can
+/// apply the result as a single undoable step.
+pub(crate) type Layout = HashMap<NodeId, Point>;
+
+/// Radius of the circle around a node's rect, which layouts that measure distances between nodes
+/// treat as the node's extent
+fn radius(rect: Rect) -> f64 {
+    rect.width().hypot(rect.height()) / 2.0
+}
+
+/// Moves nodes straight to their new positions without recording history, for layouts that are
+/// run over several steps where only the overall change should be undoable
+pub(crate) fn apply_in_place(graph: &mut DisplayGraph, layout: &Layout) {
+    for (node_id, center) in layout {
+        if let Some(node) = graph.get_node(node_id) {
+            let translation = *center - node.rect.center();
+            graph.translate_node(node_id, translation);
+        }
+    }
+}
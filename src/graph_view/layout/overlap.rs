@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+
+use druid::{Rect, Vec2};
+use rstar::{AABB, RTree};
+use rstar::primitives::{GeomWithData, Rectangle};
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::layout::{Layout, NODE_SPACING};
+
+/// Space kept clear between nodes once overlaps are removed, in scene units
+const MIN_GAP: f64 = NODE_SPACING / 4.0;
+/// Upper bound on passes, as pushing one pair apart can make new overlaps further along
+const MAX_PASSES: usize = 100;
+/// Overlaps smaller than this are rounding error from earlier passes rather than real overlaps
+const EPSILON: f64 = 1e-6;
+
+type IndexedRect = GeomWithData<Rectangle<(f64, f64)>, usize>;
+
+/// Moves nodes as little as possible so that no two overlap, leaving pinned nodes where they are.
+///
+/// Every pass pushes each overlapping pair apart along whichever axis needs the smaller move, each
+/// node going half way unless the other is pinned, and always in the direction that keeps the pair
+/// in the same order along that axis. Passes repeat until nothing overlaps. Candidate pairs come from
+/// an R-tree over the nodes' current rects, rebuilt for each pass as the nodes move.
+pub(crate) fn remove_overlaps(graph: &DisplayGraph, pinned: &HashSet<NodeId>) -> Layout {
+    let nodes = graph.nodes();
+    let mut rects: Vec<Rect> = nodes.iter().map(|n| n.rect.inset(MIN_GAP / 2.0)).collect();
+    let movable: Vec<bool> = nodes.iter().map(|n| !pinned.contains(&n.id)).collect();
+    for _ in 0..MAX_PASSES {
+        let rtree: RTree<IndexedRect> = RTree::bulk_load(rects.iter().enumerate()
+            .map(|(i, r)| IndexedRect::new(Rectangle::from_corners((r.x0, r.y0), (r.x1, r.y1)), i))
+            .collect());
+        let mut displacements = vec![Vec2::ZERO; rects.len()];
+        let mut overlapping = false;
+        for (i, a) in rects.iter().enumerate() {
+            let envelope = AABB::from_corners((a.x0, a.y0), (a.x1, a.y1));
+            for j in rtree.locate_in_envelope_intersecting(&envelope).map(|r| r.data).filter(|j| *j > i) {
+                if !movable[i] && !movable[j] {
+                    continue;
+                }
+                let separation = match separation(*a, rects[j], i, j) {
+                    Some(separation) => separation,
+                    None => continue,
+                };
+                overlapping = true;
+                let a_share = match (movable[i], movable[j]) {
+                    (true, true) => 0.5,
+                    (true, false) => 1.0,
+                    _ => 0.0,
+                };
+                displacements[i] -= separation * a_share;
+                displacements[j] += separation * (1.0 - a_share);
+            }
+        }
+        if !overlapping {
+            break;
+        }
+        for (rect, displacement) in rects.iter_mut().zip(displacements) {
+            *rect = *rect + displacement;
+        }
+    }
+    nodes.iter().zip(rects)
+        .filter(|(node, rect)| node.rect.center() != rect.center())
+        .map(|(node, rect)| (node.id, rect.center()))
+        .collect()
+}
+
+/// How far `b` has to move away from `a` for them not to overlap, or nothing if they don't.
+/// Nodes with the same centre on the chosen axis are kept in the order they're stored in.
+fn separation(a: Rect, b: Rect, a_index: usize, b_index: usize) -> Option<Vec2> {
+    let overlap = a.intersect(b);
+    if overlap.width() <= EPSILON || overlap.height() <= EPSILON {
+        return None;
+    }
+    let delta = b.center() - a.center();
+    let direction = |d: f64| if d > 0.0 || (d == 0.0 && a_index < b_index) { 1.0 } else { -1.0 };
+    // Far enough for the two to just touch, which is more than the overlap when one contains the other
+    let push_x = (a.width() + b.width()) / 2.0 - delta.x.abs();
+    let push_y = (a.height() + b.height()) / 2.0 - delta.y.abs();
+    if push_x <= push_y {
+        Some(Vec2::new(direction(delta.x) * push_x, 0.0))
+    } else {
+        Some(Vec2::new(0.0, direction(delta.y) * push_y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Point, Size};
+
+    use crate::graph::Graph;
+    use crate::graph::node::Node;
+
+    use super::*;
+
+    fn laid_out_rects(graph: &Graph, layout: &Layout) -> Vec<Rect> {
+        graph.nodes.iter()
+            .map(|n| layout.get(&n.id).map_or(n.rect, |center| Rect::from_center_size(*center, n.rect.size())))
+            .collect()
+    }
+
+    fn assert_no_overlaps(rects: &[Rect]) {
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                let overlap = a.intersect(*b);
+                assert!(overlap.width() <= EPSILON || overlap.height() <= EPSILON, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn overlapping_nodes_are_separated_in_order() {
+        let nodes: Vec<Node> = (0..5).map(|i| Node::new(Point::new(i as f64 * 30.0, i as f64 * 5.0), None)).collect();
+        let graph = Graph { nodes, edges: vec![] };
+        let layout = remove_overlaps(&DisplayGraph::from(&graph), &HashSet::new());
+        let rects = laid_out_rects(&graph, &layout);
+
+        assert_no_overlaps(&rects);
+        // Nodes pushed apart vertically can end up level, but never swap sides
+        for pair in rects.windows(2) {
+            assert!(pair[0].center().x <= pair[1].center().x + EPSILON);
+        }
+    }
+
+    #[test]
+    fn nodes_that_dont_overlap_are_left_alone() {
+        let nodes = vec![Node::new(Point::new(0.0, 0.0), None), Node::new(Point::new(200.0, 0.0), None)];
+        let layout = remove_overlaps(&DisplayGraph::from(&Graph { nodes, edges: vec![] }), &HashSet::new());
+        assert!(layout.is_empty());
+    }
+
+    #[test]
+    fn pinned_nodes_are_not_moved() {
+        let pinned_node = Node::new(Point::new(0.0, 0.0), Some(Size::new(300.0, 300.0)));
+        let nodes = vec![pinned_node.clone(), Node::new(Point::new(0.0, 0.0), None), Node::new(Point::new(10.0, 10.0), None)];
+        let graph = Graph { nodes, edges: vec![] };
+        let layout = remove_overlaps(&DisplayGraph::from(&graph), &HashSet::from([pinned_node.id]));
+
+        assert!(!layout.contains_key(&pinned_node.id));
+        assert_no_overlaps(&laid_out_rects(&graph, &layout));
+    }
+}
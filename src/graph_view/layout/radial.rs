@@ -0,0 +1,153 @@
+use std::collections::{HashMap, VecDeque};
+use std::f64::consts::{PI, TAU};
+
+use druid::Vec2;
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::layout::{Layout, NODE_SPACING, radius};
+
+/// Places `focus_id` where it is and everything connected to it on rings around it, one ring per
+/// step away from the focus, following edges in either direction.
+///
+/// Each node gets a wedge of its parent's wedge in proportion to how many leaves lie beyond it, so
+/// branches stay together, and sits in the middle of it. Each ring is then made large enough that
+/// neighbouring nodes on it don't touch and that it clears the ring inside it. Siblings keep their
+/// current order around the focus.
+pub(crate) fn radial_layout(graph: &DisplayGraph, focus_id: NodeId) -> Layout {
+    let focus = match graph.get_node(&focus_id) {
+        Some(focus) => focus,
+        None => return Layout::new(),
+    };
+    let center = focus.rect.center();
+    let current_angle = |node_id: &NodeId| {
+        let offset = graph.get_node(node_id).unwrap().rect.center() - center;
+        offset.y.atan2(offset.x)
+    };
+
+    // Breadth-first from the focus, so parents always come before their children
+    let mut order = vec![focus_id];
+    let mut rings: HashMap<NodeId, usize> = HashMap::from([(focus_id, 0)]);
+    let mut children: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    let mut queue = VecDeque::from([focus_id]);
+    while let Some(node_id) = queue.pop_front() {
+        let mut node_children = vec![];
+        for edge_id in graph.node_edge_ids(&node_id) {
+            let edge = graph.get_edge(edge_id).unwrap();
+            let neighbour_id = if edge.from_node == node_id { edge.to_node } else { edge.from_node };
+            if !rings.contains_key(&neighbour_id) {
+                rings.insert(neighbour_id, rings[&node_id] + 1);
+                node_children.push(neighbour_id);
+            }
+        }
+        node_children.sort_by(|a, b| current_angle(a).total_cmp(&current_angle(b)));
+        order.extend_from_slice(&node_children);
+        queue.extend(node_children.iter().copied());
+        children.insert(node_id, node_children);
+    }
+
+    let mut leaves: HashMap<NodeId, f64> = HashMap::new();
+    for node_id in order.iter().rev() {
+        let child_leaves: f64 = children[node_id].iter().map(|child_id| leaves[child_id]).sum();
+        leaves.insert(*node_id, child_leaves.max(1.0));
+    }
+
+    // Wedges are (start angle, size), starting with the first child of the focus where it is now
+    let mut wedges: HashMap<NodeId, (f64, f64)> = HashMap::new();
+    let start_angle = children[&focus_id].first().map_or(0.0, |first| {
+        current_angle(first) - TAU * leaves[first] / leaves[&focus_id] / 2.0
+    });
+    wedges.insert(focus_id, (start_angle, TAU));
+    for node_id in &order {
+        let (mut start, size) = wedges[node_id];
+        for child_id in &children[node_id] {
+            let child_size = size * leaves[child_id] / leaves[node_id];
+            wedges.insert(*child_id, (start, child_size));
+            start += child_size;
+        }
+    }
+
+    let ring_count = rings.values().max().unwrap() + 1;
+    let mut ring_nodes: Vec<Vec<NodeId>> = vec![vec![]; ring_count];
+    for node_id in &order {
+        ring_nodes[rings[node_id]].push(*node_id);
+    }
+    let mut radii = vec![0.0f64; ring_count];
+    let mut inner_ring_reach = radius(focus.rect);
+    for ring in 1..ring_count {
+        let ring_reach = ring_nodes[ring].iter()
+            .map(|node_id| radius(graph.get_node(node_id).unwrap().rect))
+            .fold(0.0, f64::max);
+        let clearance = radii[ring - 1] + inner_ring_reach + ring_reach + NODE_SPACING;
+        // Nodes further round the ring are further apart, so only neighbours need checking
+        let mut by_angle: Vec<(f64, f64)> = ring_nodes[ring].iter()
+            .map(|node_id| (angle(wedges[node_id]).rem_euclid(TAU), radius(graph.get_node(node_id).unwrap().rect)))
+            .collect();
+        by_angle.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let fit = if by_angle.len() < 2 { 0.0 } else {
+            (0..by_angle.len()).map(|i| {
+                let (angle_a, radius_a) = by_angle[i];
+                let (angle_b, radius_b) = by_angle[(i + 1) % by_angle.len()];
+                let between = (angle_b - angle_a).rem_euclid(TAU).min(PI);
+                // The chord between the two centres has to be long enough for both nodes
+                (radius_a + radius_b + NODE_SPACING) / (2.0 * (between / 2.0).sin())
+            }).fold(0.0, f64::max)
+        };
+        radii[ring] = clearance.max(fit);
+        inner_ring_reach = ring_reach;
+    }
+
+    order.iter().map(|node_id| {
+        let angle = angle(wedges[node_id]);
+        (*node_id, center + Vec2::new(angle.cos(), angle.sin()) * radii[rings[node_id]])
+    }).collect()
+}
+
+/// Nodes sit in the middle of their wedge
+fn angle((start, size): (f64, f64)) -> f64 {
+    start + size / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Point, Rect, Size};
+
+    use crate::graph::edge::{Edge, EdgeType};
+    use crate::graph::Graph;
+    use crate::graph::node::Node;
+
+    use super::*;
+
+    #[test]
+    fn neighbours_are_placed_on_rings_by_distance_without_overlapping() {
+        let focus = Node::new(Point::new(100.0, 100.0), None);
+        let neighbours: Vec<Node> = (0..6)
+            .map(|i| Node::new(Point::new(i as f64 * 50.0, 0.0), Some(Size::new(60.0 + i as f64 * 30.0, 40.0))))
+            .collect();
+        let outer: Vec<Node> = (0..12).map(|_| Node::new(Point::ORIGIN, None)).collect();
+        // Edges in both directions count as connections, and the cycle back to the focus is ignored
+        let mut edges: Vec<Edge> = neighbours.iter().map(|n| Edge::new(n.id, focus.id, EdgeType::Directional)).collect();
+        edges.extend(outer.iter().enumerate().map(|(i, n)| Edge::new(neighbours[i % 6].id, n.id, EdgeType::Undirected)));
+        edges.push(Edge::new(outer[0].id, focus.id, EdgeType::Undirected));
+        let nodes: Vec<Node> = [vec![focus.clone()], neighbours.clone(), outer.clone()].concat();
+        let layout = radial_layout(&DisplayGraph::from(&Graph { nodes: nodes.clone(), edges }), focus.id);
+
+        let center = focus.rect.center();
+        assert_eq!(layout[&focus.id], center);
+        let distance = |node: &Node| (layout[&node.id] - center).hypot();
+        let inner_radius = distance(&neighbours[1]);
+        for node in &neighbours[1..] {
+            assert!((distance(node) - inner_radius).abs() < 1e-9);
+        }
+        // outer[0] is next to the focus, so it's on the inner ring too
+        assert!((distance(&outer[0]) - inner_radius).abs() < 1e-9);
+        assert!(outer[1..].iter().all(|node| distance(node) > inner_radius + 60.0));
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                let overlap = Rect::from_center_size(layout[&a.id], a.rect.size())
+                    .intersect(Rect::from_center_size(layout[&b.id], b.rect.size()));
+                assert_eq!(overlap.area(), 0.0, "nodes overlap");
+            }
+        }
+    }
+}
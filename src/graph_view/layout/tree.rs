@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use druid::{Point, Size};
+
+use crate::graph::node::NodeId;
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::layout::{Layout, NODE_SPACING};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum TreeDirection {
+    LeftToRight,
+    TopDown,
+    /// Left-to-right with the root's children split between its right and left sides
+    Balanced,
+}
+
+/// Lays out everything reachable from `root_id` by following edges from their `from` to their `to`
+/// node as a tidy tree, keeping the root where it is.
+///
+/// Each level lines up on the depth axis, spaced by the largest node in that level, and every subtree
+/// gets a band along the breadth axis wide enough for all of its descendants, with its root centred
+/// on the band. Where nodes are reachable along several paths (shared children or cycles) only the
+/// shortest is kept, so each node is placed once. Siblings keep their current order.
+pub(crate) fn tree_layout(graph: &DisplayGraph, root_id: NodeId, direction: TreeDirection) -> Layout {
+    let root = match graph.get_node(&root_id) {
+        Some(root) => root,
+        None => return Layout::new(),
+    };
+    // Work in (depth, breadth) coordinates and only map them to x and y at the end
+    let to_axes = |size: Size| match direction {
+        TreeDirection::TopDown => (size.height, size.width),
+        TreeDirection::LeftToRight | TreeDirection::Balanced => (size.width, size.height),
+    };
+    let breadth_of = |point: Point| match direction {
+        TreeDirection::TopDown => point.x,
+        TreeDirection::LeftToRight | TreeDirection::Balanced => point.y,
+    };
+
+    // Breadth-first, so parents always come before their children
+    let mut order = vec![root_id];
+    let mut depths: HashMap<NodeId, usize> = HashMap::from([(root_id, 0)]);
+    let mut children: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    let mut queue = VecDeque::from([root_id]);
+    while let Some(node_id) = queue.pop_front() {
+        let mut node_children: Vec<NodeId> = graph.node_edge_ids(&node_id).iter()
+            .map(|edge_id| graph.get_edge(edge_id).unwrap())
+            .filter(|edge| edge.from_node == node_id && !depths.contains_key(&edge.to_node))
+            .map(|edge| edge.to_node)
+            .collect();
+        let mut seen = HashSet::new();
+        node_children.retain(|child_id| seen.insert(*child_id));
+        node_children.sort_by(|a, b| {
+            let breadth = |id| breadth_of(graph.get_node(id).unwrap().rect.center());
+            breadth(a).total_cmp(&breadth(b))
+        });
+        for child_id in &node_children {
+            depths.insert(*child_id, depths[&node_id] + 1);
+            order.push(*child_id);
+            queue.push_back(*child_id);
+        }
+        children.insert(node_id, node_children);
+    }
+    let extents: HashMap<NodeId, (f64, f64)> = order.iter()
+        .map(|node_id| (*node_id, to_axes(graph.get_node(node_id).unwrap().rect.size())))
+        .collect();
+
+    // Size of each subtree's band along the breadth axis, children first
+    let mut bands: HashMap<NodeId, f64> = HashMap::new();
+    let children_span = |node_id: &NodeId, bands: &HashMap<NodeId, f64>| span(&children[node_id], bands);
+    for node_id in order.iter().rev() {
+        bands.insert(*node_id, extents[node_id].1.max(children_span(node_id, &bands)));
+    }
+
+    // Which side of the root each subtree is on - only the balanced layout uses the left (-1.0) side
+    let mut sides: HashMap<NodeId, f64> = HashMap::from([(root_id, 1.0)]);
+    // Start of each subtree's band along the breadth axis, relative to the root's centre
+    let mut band_starts: HashMap<NodeId, f64> = HashMap::new();
+    if direction == TreeDirection::Balanced {
+        let (right, left) = split_balanced(&children[&root_id], &bands);
+        for (side, side_children) in [(1.0, right), (-1.0, left)] {
+            place_children(&side_children, -span(&side_children, &bands) / 2.0, &bands, &mut band_starts);
+            for child_id in side_children {
+                sides.insert(child_id, side);
+            }
+        }
+    } else {
+        band_starts.insert(root_id, -bands[&root_id] / 2.0);
+    }
+    for node_id in &order {
+        if let Some(start) = band_starts.get(node_id).copied() {
+            let node_children = &children[node_id];
+            let children_start = start + (bands[node_id] - span(node_children, &bands)) / 2.0;
+            place_children(node_children, children_start, &bands, &mut band_starts);
+        }
+        let side = sides[node_id];
+        for child_id in &children[node_id] {
+            sides.entry(*child_id).or_insert(side);
+        }
+    }
+
+    // Every level is as deep as its deepest node, with the root's level centred on the root
+    let level_count = depths.values().max().unwrap() + 1;
+    let mut level_extents = vec![0.0f64; level_count];
+    for node_id in &order {
+        let level = depths[node_id];
+        level_extents[level] = level_extents[level].max(extents[node_id].0);
+    }
+    let mut level_centers = Vec::with_capacity(level_count);
+    let mut level_start = -level_extents[0] / 2.0;
+    for level_extent in &level_extents {
+        level_centers.push(level_start + level_extent / 2.0);
+        level_start += level_extent + NODE_SPACING;
+    }
+
+    let root_center = root.rect.center();
+    order.iter().map(|node_id| {
+        let depth = sides[node_id] * level_centers[depths[node_id]];
+        let breadth = if *node_id == root_id { 0.0 } else { band_starts[node_id] + bands[node_id] / 2.0 };
+        let offset = match direction {
+            TreeDirection::TopDown => (breadth, depth),
+            TreeDirection::LeftToRight | TreeDirection::Balanced => (depth, breadth),
+        };
+        (*node_id, root_center + offset)
+    }).collect()
+}
+
+/// Total breadth of a row of sibling subtrees, including the space between them
+fn span(siblings: &[NodeId], bands: &HashMap<NodeId, f64>) -> f64 {
+    let gaps = siblings.len().saturating_sub(1) as f64 * NODE_SPACING;
+    siblings.iter().map(|id| bands[id]).sum::<f64>() + gaps
+}
+
+/// Lays sibling subtrees out one after another along the breadth axis
+fn place_children(siblings: &[NodeId], start: f64, bands: &HashMap<NodeId, f64>, band_starts: &mut HashMap<NodeId, f64>) {
+    let mut cursor = start;
+    for child_id in siblings {
+        band_starts.insert(*child_id, cursor);
+        cursor += bands[child_id] + NODE_SPACING;
+    }
+}
+
+/// Splits the root's children into a right and a left group of about the same breadth, keeping their order
+fn split_balanced(siblings: &[NodeId], bands: &HashMap<NodeId, f64>) -> (Vec<NodeId>, Vec<NodeId>) {
+    let imbalance = |split_at: usize| (span(&siblings[..split_at], bands) - span(&siblings[split_at..], bands)).abs();
+    let split_at = (1..=siblings.len())
+        .min_by(|a, b| imbalance(*a).total_cmp(&imbalance(*b)))
+        .unwrap_or(0);
+    (siblings[..split_at].to_vec(), siblings[split_at..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Rect;
+
+    use crate::graph::Graph;
+    use crate::graph::node::Node;
+    use crate::graph_view::test_util::{edge, node};
+
+    use super::*;
+
+    fn laid_out_rect(layout: &Layout, node: &Node) -> Rect {
+        Rect::from_center_size(layout[&node.id], node.rect.size())
+    }
+
+    fn assert_no_overlaps(layout: &Layout, nodes: &[&Node]) {
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                let overlap = laid_out_rect(layout, a).intersect(laid_out_rect(layout, b));
+                assert!(overlap.width() <= 0.0 || overlap.height() <= 0.0, "{:?} overlaps {:?}", a.id, b.id);
+            }
+        }
+    }
+
+    #[test]
+    fn children_are_placed_right_of_their_parent_in_their_current_order() {
+        let root = node((0.0, 0.0), (100.0, 60.0));
+        let wide = node((500.0, 300.0), (300.0, 60.0));
+        let tall = node((500.0, -300.0), (100.0, 200.0));
+        let grandchild = node((0.0, 500.0), (100.0, 60.0));
+        // The edge back to the root makes a cycle, which must not be followed
+        let edges = vec![edge(&root, &wide), edge(&root, &tall), edge(&wide, &grandchild), edge(&grandchild, &root)];
+        let graph = Graph { nodes: vec![root.clone(), wide.clone(), tall.clone(), grandchild.clone()], edges };
+        let layout = tree_layout(&DisplayGraph::from(&graph), root.id, TreeDirection::LeftToRight);
+
+        assert_eq!(layout.len(), 4);
+        assert_eq!(layout[&root.id], root.rect.center());
+        // Both children share a level, centred on the widest of them, right of the root
+        assert_eq!(layout[&wide.id].x, layout[&tall.id].x);
+        assert_eq!(laid_out_rect(&layout, &wide).x0, root.rect.x1 + NODE_SPACING);
+        assert!(layout[&tall.id].y < layout[&wide.id].y);
+        assert_eq!(laid_out_rect(&layout, &grandchild).x0, laid_out_rect(&layout, &wide).x1 + NODE_SPACING);
+        assert_eq!(layout[&grandchild.id].y, layout[&wide.id].y);
+        assert_no_overlaps(&layout, &[&root, &wide, &tall, &grandchild]);
+    }
+
+    #[test]
+    fn top_down_trees_grow_downwards() {
+        let root = node((0.0, 0.0), (100.0, 60.0));
+        let children: Vec<Node> = (0..3).map(|i| node((i as f64 * 10.0, 0.0), (100.0, 60.0))).collect();
+        let edges = children.iter().map(|child| edge(&root, child)).collect();
+        let nodes = [vec![root.clone()], children.clone()].concat();
+        let layout = tree_layout(&DisplayGraph::from(&Graph { nodes, edges }), root.id, TreeDirection::TopDown);
+
+        assert_eq!(layout[&children[1].id], root.rect.center() + (0.0, 60.0 + NODE_SPACING));
+        assert_eq!(layout[&children[0].id].x, root.rect.center().x - 100.0 - NODE_SPACING);
+        assert_eq!(layout[&children[2].id].x, root.rect.center().x + 100.0 + NODE_SPACING);
+    }
+
+    #[test]
+    fn balanced_trees_put_children_on_both_sides() {
+        let root = node((0.0, 0.0), (100.0, 60.0));
+        let children: Vec<Node> = (0..4).map(|i| node((200.0, i as f64 * 100.0), (100.0, 60.0))).collect();
+        let edges = children.iter().map(|child| edge(&root, child)).collect();
+        let nodes = [vec![root.clone()], children.clone()].concat();
+        let layout = tree_layout(&DisplayGraph::from(&Graph { nodes, edges }), root.id, TreeDirection::Balanced);
+
+        let center = root.rect.center();
+        let right: Vec<&Node> = children.iter().filter(|c| layout[&c.id].x > center.x).collect();
+        let left: Vec<&Node> = children.iter().filter(|c| layout[&c.id].x < center.x).collect();
+        assert_eq!((right.len(), left.len()), (2, 2));
+        assert_eq!(layout[&right[0].id].y + layout[&right[1].id].y, 2.0 * center.y);
+        assert_no_overlaps(&layout, &[&root, &children[0], &children[1], &children[2], &children[3]]);
+    }
+}
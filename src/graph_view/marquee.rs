@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use druid::{Point, Rect};
+
+use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::element_id::ElementId;
+
+/// How the elements inside a marquee combine with the selection from before the drag started
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum SelectionMode {
+    Replace,
+    Add,
+    Toggle,
+}
+
+/// Rubber-band selection rectangle, in scene coordinates
+pub(crate) struct Marquee {
+    pub(crate) start: Point,
+    pub(crate) end: Point,
+    pub(crate) mode: SelectionMode,
+    pub(crate) selection_before: HashSet<ElementId>,
+}
+
+impl Marquee {
+    pub(crate) fn new(start: Point, mode: SelectionMode, selection_before: HashSet<ElementId>) -> Self {
+        Marquee { start, end: start, mode, selection_before }
+    }
+
+    pub(crate) fn rect(&self) -> Rect {
+        Rect::from_points(self.start, self.end)
+    }
+
+    /// Dragging right-to-left selects everything the marquee touches,
+    /// dragging left-to-right only selects elements entirely inside it
+    pub(crate) fn is_crossing(&self) -> bool {
+        self.end.x < self.start.x
+    }
+
+    pub(crate) fn selection(&self, display_graph: &DisplayGraph) -> HashSet<ElementId> {
+        let in_marquee = display_graph.elements_in_rect(self.rect(), self.is_crossing());
+        match self.mode {
+            SelectionMode::Replace => in_marquee.into_iter().collect(),
+            SelectionMode::Add => self.selection_before.iter().copied().chain(in_marquee).collect(),
+            SelectionMode::Toggle => {
+                let in_marquee: HashSet<ElementId> = in_marquee.into_iter().collect();
+                self.selection_before.symmetric_difference(&in_marquee).copied().collect()
+            }
+        }
+    }
+}
@@ -1,24 +1,76 @@
 use std::collections::HashSet;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
 
 use druid::*;
-use druid::kurbo::Line;
-use druid::piet::{StrokeStyle, Text, TextLayout, TextLayoutBuilder};
-use uuid::Uuid;
+use druid::kurbo::{BezPath, Circle, Line};
+use druid::piet::{Piet, PietText, PietTextLayout, StrokeStyle, Text, TextLayout, TextLayoutBuilder};
 
 use viewport::Viewport;
 
-use crate::graph::edge::{Edge, EdgeId};
+use crate::graph::document;
+use crate::graph::edge::{Edge, EdgeId, EdgeType};
+use crate::graph::Graph;
 use crate::graph::node::{Node, NodeId};
+use crate::graph::style::{NodeStyle, ThemeColor};
+use crate::graph_view::arrange::{Alignment, Axis};
 use crate::graph_view::display_graph::DisplayGraph;
+use crate::graph_view::display_graph::edge::DisplayEdge;
+use crate::graph_view::display_graph::node::DisplayNode;
+use crate::graph_view::display_graph::shape;
 use crate::graph_view::drag_state::DragState;
 use crate::graph_view::element_id::ElementId;
+use crate::graph_view::grid::{GRID_SPACING, GridSnap, SnapTarget};
+use crate::graph_view::guides::Guides;
+use crate::graph_view::history::{GraphEdit, History};
+use crate::graph_view::layout::{apply_in_place, Layout};
+use crate::graph_view::layout::force::{self, ForceLayout};
+use crate::graph_view::layout::layered::{layered_layout, LayerDirection};
+use crate::graph_view::layout::overlap::remove_overlaps;
+use crate::graph_view::layout::radial::radial_layout;
+use crate::graph_view::layout::tree::{tree_layout, TreeDirection};
+use crate::graph_view::marquee::{Marquee, SelectionMode};
+use crate::graph_view::resize::{Handle, resize};
+use crate::graph_view::style::StyleCommand;
+use crate::graph_view::text_edit::{EditOutcome, TextEdit};
+use crate::theme::{self, Theme};
 
 mod viewport;
 mod drag_state;
 mod display_graph;
 mod example_graphs;
 mod element_id;
+mod text_edit;
+mod history;
+mod marquee;
+mod layout;
+mod grid;
+mod guides;
+mod arrange;
+mod resize;
+mod auto_size;
+mod style;
+#[cfg(test)]
+mod test_util;
+#[doc(hidden)]
+pub mod bench;
+
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+/// Space between a node's border and its text, in scene units
+const NODE_TEXT_PADDING: f64 = 4.0;
+/// How far from an edge a click can be and still select it, in screen pixels
+const EDGE_HIT_TOLERANCE: f64 = 6.0;
+/// How close dragged nodes have to be to lining up with another node to snap to it, in screen pixels
+const GUIDE_SNAP_DISTANCE: f64 = 6.0;
+/// Width of the resize handles drawn around selected nodes, in screen pixels
+const HANDLE_SIZE: f64 = 8.0;
+/// How far outside the window elements are still painted, in scene units,
+/// so strokes and highlights that extend past an element's bounds aren't cut off at the edges
+const PAINT_MARGIN: f64 = 8.0;
+
+/// Shows a message in the view's error banner until the next click or key press
+pub(crate) const SHOW_ERROR: Selector<String> = Selector::new("tree-rs.show-error");
+
+const DOCUMENT_FILE_SPEC: FileSpec = FileSpec::new("Tree document", &[document::FILE_EXTENSION]);
 
 #[derive(Default)]
 pub struct GraphView {
@@ -27,6 +79,24 @@ pub struct GraphView {
     display_graph: DisplayGraph,
     selection: HashSet<ElementId>,
     new_edge: Option<(NodeId, Line)>,
+    text_edit: Option<TextEdit>,
+    history: History,
+    document_path: Option<PathBuf>,
+    /// The last failure to report, like an open or save that failed, shown until the next click or key press
+    error_message: Option<String>,
+    /// Nodes that layouts leave where they are
+    pinned: HashSet<NodeId>,
+    force_animation: Option<ForceAnimation>,
+    /// Tidy overlaps away whenever a node is added
+    auto_tidy: bool,
+    grid_snap: GridSnap,
+}
+
+/// A force layout that moves the graph a step each frame, with the nodes as they were before it
+/// started so that the whole run can be undone in one go. Only their rects end up in the undo step.
+struct ForceAnimation {
+    layout: ForceLayout,
+    nodes_before: Vec<Node>,
 }
 
 impl GraphView {
@@ -34,81 +104,649 @@ impl GraphView {
         GraphView::default()
     }
 
-    fn paint_dot_grid(&self, ctx: &mut PaintCtx) {
-        const DOT_COLOUR: Color = Color::rgb8(0x61, 0x61, 0x61);
-        const GRID_SPACING: f64 = 36.0;
-        const DOT_SIZE: f64 = 2.0;
-        // Used to ensure we're not looking at half dots when at (0, 0)
-        let base_offset = GRID_SPACING / 2.0;
-        // Includes dot size to ensure x & y are aligned
-        let x_offset = base_offset - self.viewport.origin.x - DOT_SIZE / 2.0;
-        let y_offset = (base_offset - self.viewport.origin.y) % GRID_SPACING;
+    fn document_dialog_options() -> FileDialogOptions {
+        FileDialogOptions::new()
+            .allowed_types(vec![DOCUMENT_FILE_SPEC])
+            .default_type(DOCUMENT_FILE_SPEC)
+    }
+
+    /// Replaces the whole document, discarding any state that refers to the old one
+    fn set_display_graph(&mut self, display_graph: DisplayGraph, document_path: Option<PathBuf>) {
+        self.display_graph = display_graph;
+        self.drag_state = None;
+        self.new_edge = None;
+        self.force_animation = None;
+        self.pinned.clear();
+        self.selection.clear();
+        self.text_edit = None;
+        self.history.clear();
+        self.document_path = document_path;
+    }
+
+    fn open_document(&mut self, path: &Path) {
+        match document::load(path) {
+            Ok(graph) => {
+                self.set_display_graph(DisplayGraph::from(&graph), Some(path.to_path_buf()));
+                self.error_message = None;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to open {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Saves the document to `path`, which becomes the document's path only if the save works
+    fn save_document(&mut self, path: &Path) {
+        match document::save(&Graph::from(&self.display_graph), path) {
+            Ok(()) => {
+                self.document_path = Some(path.to_path_buf());
+                self.error_message = None;
+            }
+            Err(e) => self.error_message = Some(format!("Failed to save {}: {}", path.display(), e)),
+        }
+    }
+
+    fn delete_selection(&mut self) {
+        self.finish_force_layout();
+        let transaction = history::removal(&self.display_graph, self.selection.drain());
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    fn undo(&mut self) {
+        self.cancel_drag();
+        self.finish_text_edit();
+        self.finish_force_layout();
+        self.history.undo(&mut self.display_graph);
+        self.selection.retain(|elem_ref| self.display_graph.contains(elem_ref));
+    }
+
+    fn redo(&mut self) {
+        self.cancel_drag();
+        self.finish_text_edit();
+        self.finish_force_layout();
+        self.history.redo(&mut self.display_graph);
+        self.selection.retain(|elem_ref| self.display_graph.contains(elem_ref));
+    }
+
+    /// Abandons the drag in progress, putting back a node that was being resized, so that the rest of
+    /// the drag can't act on nodes that have since gone
+    fn cancel_drag(&mut self) {
+        if let Some(drag) = self.drag_state.take() {
+            if drag.resize_handle.is_some() {
+                for before in &drag.nodes_before {
+                    self.display_graph.set_node_rect(&before.id, before.rect);
+                }
+            }
+        }
+        self.new_edge = None;
+    }
+
+    /// Connects the node the new edge was dragged from to the node at `scene_pos`, if both are still there
+    fn finish_new_edge(&mut self, scene_pos: Point) {
+        if let Some((start_node_id, _)) = self.new_edge.take() {
+            if self.display_graph.get_node(&start_node_id).is_none() {
+                return;
+            }
+            if let Some(end_node) = self.display_graph.get_node_at_point((scene_pos.x, scene_pos.y)) {
+                let edge = Edge::new(start_node_id, end_node.id, EdgeType::default());
+                let index = self.display_graph.edge_count();
+                self.history.apply(&mut self.display_graph, vec![GraphEdit::AddEdge { edge, index }]);
+            }
+        }
+    }
 
+    /// Cycles the type of every selected edge, along with edges whose nodes are both selected
+    fn cycle_selected_edge_types(&mut self) {
+        let mut edge_ids: Vec<EdgeId> = vec![];
+        for elem_ref in &self.selection {
+            match elem_ref {
+                ElementId::Node(node_id) => edge_ids.extend(self.display_graph.node_edge_ids(node_id).iter()
+                    .filter(|edge_id| {
+                        let edge = self.display_graph.get_edge(edge_id).unwrap();
+                        self.selection.contains(&ElementId::Node(edge.from_node))
+                            && self.selection.contains(&ElementId::Node(edge.to_node))
+                    })),
+                ElementId::Edge(edge_id) => edge_ids.push(*edge_id),
+            }
+        }
+        let mut seen_edge_ids = HashSet::new();
+        edge_ids.retain(|edge_id| seen_edge_ids.insert(*edge_id));
+        let transaction = edge_ids.iter().map(|edge_id| {
+            let before = Edge::from(self.display_graph.get_edge(edge_id).unwrap());
+            let after = Edge { edge_type: before.edge_type.next(), ..before.clone() };
+            GraphEdit::UpdateEdge { before, after }
+        }).collect();
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    /// Cycles the auto-size policy of every selected node, resizing them to fit their text under the new policy
+    fn cycle_selected_auto_sizes(&mut self, text_factory: &mut PietText) {
+        self.finish_text_edit();
+        self.finish_force_layout();
+        let transaction = self.display_graph.nodes().into_iter()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .map(|n| {
+                let before = Node::from(n);
+                let auto_size = before.auto_size.next();
+                let rect = auto_size::fit(text_factory, &before.text, &before.style, auto_size, before.rect, None);
+                GraphEdit::UpdateNode { after: Node { auto_size, rect, ..before.clone() }, before }
+            }).collect();
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    /// Cycles the shape of every selected node
+    fn cycle_selected_shapes(&mut self) {
+        self.finish_text_edit();
+        self.finish_force_layout();
+        let transaction = self.display_graph.nodes().into_iter()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .map(|n| {
+                let before = Node::from(n);
+                GraphEdit::UpdateNode { after: Node { shape: before.shape.next(), ..before.clone() }, before }
+            }).collect();
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    /// Restyles every selected node and edge as one undoable step. Nodes that size themselves to
+    /// their text are refitted, as their font may have changed.
+    fn restyle_selection(&mut self, text_factory: &mut PietText, command: StyleCommand) {
+        self.finish_text_edit();
+        self.finish_force_layout();
+        let selected_nodes: Vec<&DisplayNode> = self.display_graph.nodes().into_iter()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .collect();
+        let selected_edges: Vec<&DisplayEdge> = self.display_graph.edges().into_iter()
+            .filter(|e| self.selection.contains(&ElementId::Edge(e.id)))
+            .collect();
+        let current = match (selected_nodes.first(), selected_edges.first()) {
+            (Some(n), _) => n.style.clone(),
+            (None, Some(e)) => NodeStyle { line: e.style.clone(), ..NodeStyle::default() },
+            (None, None) => return,
+        };
+        let change = command.change(&current);
+        let node_edits = selected_nodes.into_iter().map(|n| {
+            let before = Node::from(n);
+            let mut after = before.clone();
+            change.apply_to_node(&mut after.style);
+            after.rect = auto_size::fit(text_factory, &after.text, &after.style, after.auto_size, after.rect, None);
+            (after != before).then_some(GraphEdit::UpdateNode { before, after })
+        });
+        let edge_edits = selected_edges.into_iter().map(|e| {
+            let before = Edge::from(e);
+            let mut after = before.clone();
+            change.apply_to_line(&mut after.style);
+            (after != before).then_some(GraphEdit::UpdateEdge { before, after })
+        });
+        let transaction = node_edits.chain(edge_edits).flatten().collect();
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    /// Moves nodes to the positions chosen by a layout as one undoable step
+    fn apply_layout(&mut self, layout: Layout) {
+        self.finish_text_edit();
+        self.finish_force_layout();
+        let transaction = self.layout_edits(layout);
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    fn layout_edits(&self, layout: Layout) -> Vec<GraphEdit> {
+        let rects = layout.into_iter().filter_map(|(node_id, center)| {
+            let size = self.display_graph.get_node(&node_id)?.rect.size();
+            Some((node_id, Rect::from_center_size(center, size)))
+        }).collect();
+        self.node_rect_edits(rects)
+    }
+
+    fn node_rect_edits(&self, rects: Vec<(NodeId, Rect)>) -> Vec<GraphEdit> {
+        rects.into_iter().filter_map(|(node_id, rect)| {
+            let before = Node::from(self.display_graph.get_node(&node_id)?);
+            let after = Node { rect, ..before.clone() };
+            (after.rect != before.rect).then_some(GraphEdit::UpdateNode { before, after })
+        }).collect()
+    }
+
+    /// Moves or resizes the selected nodes as one undoable step, given their current rects
+    fn arrange_selection(&mut self, arrange: impl FnOnce(&[(NodeId, Rect)]) -> Vec<(NodeId, Rect)>) {
+        let selected: Vec<(NodeId, Rect)> = self.display_graph.nodes().into_iter()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .map(|n| (n.id, n.rect))
+            .collect();
+        if selected.len() < 2 {
+            return;
+        }
+        self.finish_text_edit();
+        self.finish_force_layout();
+        let transaction = self.node_rect_edits(arrange(&selected));
+        self.history.apply(&mut self.display_graph, transaction);
+    }
+
+    /// Moves nodes apart so none of them overlap, as part of the last undo step if `amend` is set
+    fn tidy_overlaps(&mut self, amend: bool) {
+        let edits = self.layout_edits(remove_overlaps(&self.display_graph, &self.pinned));
+        if amend {
+            self.history.amend(&mut self.display_graph, edits);
+        } else {
+            self.finish_text_edit();
+            self.finish_force_layout();
+            self.history.apply(&mut self.display_graph, edits);
+        }
+    }
+
+    /// Arranges everything below the single selected node as a tree
+    fn layout_selected_tree(&mut self, direction: TreeDirection) {
+        if let Some(root_id) = self.single_selected_node() {
+            self.apply_layout(tree_layout(&self.display_graph, root_id, direction));
+        }
+    }
+
+    /// Arranges everything connected to the single selected node in rings around it
+    fn layout_radially(&mut self) {
+        if let Some(focus_id) = self.single_selected_node() {
+            self.apply_layout(radial_layout(&self.display_graph, focus_id));
+        }
+    }
+
+    fn single_selected_node(&self) -> Option<NodeId> {
+        match self.selection.iter().copied().collect::<Vec<_>>()[..] {
+            [ElementId::Node(node_id)] => Some(node_id),
+            _ => None,
+        }
+    }
+
+    /// Arranges the selected nodes in layers, or the whole graph if fewer than two nodes are selected
+    fn layout_layers(&mut self, direction: LayerDirection) {
+        let mut node_ids: Vec<NodeId> = self.display_graph.nodes().into_iter()
+            .map(|n| n.id)
+            .filter(|node_id| self.selection.contains(&ElementId::Node(*node_id)))
+            .collect();
+        if node_ids.len() < 2 {
+            node_ids = self.display_graph.nodes().into_iter().map(|n| n.id).collect();
+        }
+        self.apply_layout(layered_layout(&self.display_graph, &node_ids, direction));
+    }
+
+    fn start_force_layout(&mut self) {
+        self.finish_text_edit();
+        if self.force_animation.is_none() {
+            let nodes_before = self.display_graph.nodes().into_iter().map(Node::from).collect();
+            self.force_animation = Some(ForceAnimation { layout: ForceLayout::new(), nodes_before });
+        }
+    }
+
+    /// Moves the graph one step further through the force layout, returning true once it has settled
+    fn step_force_layout(&mut self) -> bool {
+        match &mut self.force_animation {
+            Some(animation) => {
+                let layout = animation.layout.step(&self.display_graph, &self.pinned);
+                apply_in_place(&mut self.display_graph, &layout);
+                animation.layout.is_settled()
+            }
+            None => true,
+        }
+    }
+
+    /// Stops the force layout, keeping the nodes where they've got to as one undo step
+    fn finish_force_layout(&mut self) {
+        if let Some(animation) = self.force_animation.take() {
+            let transaction = self.geometry_edits(animation.nodes_before);
+            self.history.record(transaction);
+        }
+    }
+
+    /// Edits that move nodes back from where they were in `nodes_before` to where they are now.
+    /// Only the rects are taken from the snapshots, so undoing the move can't revert other changes
+    /// made to the nodes since the snapshots were taken.
+    fn geometry_edits(&self, nodes_before: Vec<Node>) -> Vec<GraphEdit> {
+        nodes_before.into_iter().filter_map(|snapshot| {
+            let after = Node::from(self.display_graph.get_node(&snapshot.id)?);
+            let before = Node { rect: snapshot.rect, ..after.clone() };
+            (after.rect != before.rect).then_some(GraphEdit::UpdateNode { before, after })
+        }).collect()
+    }
+
+    fn run_force_layout_to_convergence(&mut self) {
+        self.start_force_layout();
+        for _ in 0..force::MAX_STEPS {
+            if self.step_force_layout() {
+                break;
+            }
+        }
+        self.finish_force_layout();
+    }
+
+    /// Pins every selected node, or unpins them if they're all pinned already
+    fn toggle_selected_pins(&mut self) {
+        let selected_nodes: Vec<NodeId> = self.selection.iter().filter_map(|elem_ref| match elem_ref {
+            ElementId::Node(node_id) => Some(*node_id),
+            ElementId::Edge(_) => None,
+        }).collect();
+        if selected_nodes.iter().all(|node_id| self.pinned.contains(node_id)) {
+            for node_id in &selected_nodes {
+                self.pinned.remove(node_id);
+            }
+        } else {
+            self.pinned.extend(selected_nodes);
+        }
+    }
+
+    fn start_text_edit(&mut self, node_id: NodeId) {
+        self.finish_force_layout();
+        let original = Node::from(self.display_graph.get_node(&node_id).unwrap());
+        self.selection.clear();
+        self.selection.insert(ElementId::Node(node_id));
+        self.text_edit = Some(TextEdit::new(original));
+    }
+
+    fn finish_text_edit(&mut self) {
+        // Edits are written through to the display graph as they're made, so only the undo step is left to record
+        if let Some(edit) = self.text_edit.take() {
+            if let Some(node) = self.display_graph.get_node(&edit.node_id) {
+                let after = Node::from(node);
+                if after != edit.original {
+                    self.history.record(vec![GraphEdit::UpdateNode { before: edit.original, after }]);
+                }
+            }
+        }
+    }
+
+    /// Lays out a node's text the same way for painting and for hit-testing the text editor
+    fn node_text_layout(&self, text_factory: &mut PietText, text: &str, node_style: &NodeStyle, screen_rect: Rect, env: &Env)
+                        -> PietTextLayout {
+        let max_width = screen_rect.width() - 2.0 * NODE_TEXT_PADDING * self.viewport.scale;
+        style::node_text_layout(text_factory, text, node_style, self.viewport.scale, max_width)
+            .text_color(env.get(theme::NODE_TEXT))
+            .build().unwrap()
+    }
+
+    /// Screen position at which a node's text layout is drawn so it's centred in the node
+    fn node_text_origin(&self, text_layout: &PietTextLayout, screen_rect: Rect) -> Point {
+        let vertical_align_offset = screen_rect.height() / 2.0 - text_layout.size().height / 2.0;
+        Point::new(screen_rect.x0 + NODE_TEXT_PADDING * self.viewport.scale, screen_rect.y0 + vertical_align_offset)
+    }
+
+    fn is_over_text_edit(&self, scene_pos: Point) -> bool {
+        self.text_edit.as_ref()
+            .and_then(|edit| self.display_graph.get_node(&edit.node_id))
+            .is_some_and(|node| node.rect.contains(scene_pos))
+    }
+
+    /// Offset of the text position closest to the given screen position in the node being edited
+    fn text_edit_offset(&self, text_factory: &mut PietText, screen_pos: Point, env: &Env) -> Option<usize> {
+        let edit = self.text_edit.as_ref()?;
+        let node = self.display_graph.get_node(&edit.node_id)?;
+        let screen_rect = self.viewport.scene_rect_to_screen(node.rect);
+        let text_layout = self.node_text_layout(text_factory, &edit.text, &node.style, screen_rect, env);
+        let text_origin = self.node_text_origin(&text_layout, screen_rect);
+        Some(text_layout.hit_test_point(screen_pos - text_origin.to_vec2()).idx)
+    }
+
+    fn paint_dot_grid(&self, ctx: &mut Piet, size: Size, env: &Env) {
+        const DOT_SIZE: f64 = 2.0;
         let scale = self.viewport.scale;
-        let dotted_style = StrokeStyle::new().dash(vec![DOT_SIZE * scale, GRID_SPACING * scale], -x_offset / 2.0);
-        let grid_area = ctx.size().to_rect();
+        // Each dot is a dash centred on a grid point, so the dash and the gap after it add up to the spacing
+        let dotted_style = StrokeStyle::new().dash(vec![DOT_SIZE * scale, (GRID_SPACING - DOT_SIZE) * scale], 0.0);
+        let grid_area = size.to_rect();
+        let visible_rect = self.viewport.visible_scene_rect(size);
+        let first_column = grid::grid_lines(visible_rect.x0, visible_rect.x1).next().unwrap();
+        let line_start = self.viewport.scene_coord_to_screen(Point::new(first_column - DOT_SIZE / 2.0, 0.0)).x;
 
-        for y in (0..(grid_area.height() / (GRID_SPACING * scale)).ceil() as i64).map(|y_step| ((y_step as f64) * GRID_SPACING + y_offset) * scale) {
-            ctx.stroke_styled(Line::new((grid_area.x0, y), (grid_area.x1, y)), &DOT_COLOUR, scale * DOT_SIZE, &dotted_style)
+        for y in grid::grid_lines(visible_rect.y0, visible_rect.y1) {
+            let y = self.viewport.scene_coord_to_screen(Point::new(0.0, y)).y;
+            ctx.stroke_styled(Line::new((line_start, y), (grid_area.x1, y)), &env.get(theme::GRID_DOT), scale * DOT_SIZE, &dotted_style)
         }
     }
 
-    fn paint_origin_marker(&self, ctx: &mut PaintCtx) {
-        for line in vec![
+    fn paint_origin_marker(&self, ctx: &mut Piet, env: &Env) {
+        for line in [
             Line::new(
                 self.viewport.scene_coord_to_screen(Point::new(-10.0, -10.0)),
                 self.viewport.scene_coord_to_screen(Point::new(10.0, 10.0)),
             ),
             Line::new(self.viewport.scene_coord_to_screen(Point::new(-10.0, 10.0)),
                       self.viewport.scene_coord_to_screen(Point::new(10.0, -10.0))),
-        ] { ctx.stroke(line, &Color::BLUE, 2.0 * self.viewport.scale); }
+        ] { ctx.stroke(line, &env.get(theme::ORIGIN_MARKER), 2.0 * self.viewport.scale); }
+    }
+
+    fn paint_edges(&self, ctx: &mut Piet, edges: &[&DisplayEdge], env: &Env) {
+        for e in edges {
+            self.paint_edge(ctx, e, &style::stroke(&e.style, theme::EDGE_STROKE, env), style::stroke_width(&e.style, self.viewport.scale),
+                            &style::stroke_style(&e.style, self.viewport.scale));
+        }
+    }
+
+    fn paint_edge(&self, ctx: &mut Piet, e: &DisplayEdge, color: &Color, width: f64, stroke_style: &StrokeStyle) {
+        let line = self.viewport.scene_line_to_screen(Line::new(e.start_point, e.end_point));
+        ctx.stroke_styled(line, color, width, stroke_style);
+        if matches!(e.edge_type, EdgeType::Directional | EdgeType::Bidirectional) {
+            ctx.fill(self.arrowhead(line.p0, line.p1), color);
+        }
+        if e.edge_type == EdgeType::Bidirectional {
+            ctx.fill(self.arrowhead(line.p1, line.p0), color);
+        }
+    }
+
+    /// Triangle pointing at `tip` along the line from `tail`, in screen coordinates
+    fn arrowhead(&self, tail: Point, tip: Point) -> BezPath {
+        const ARROW_LENGTH: f64 = 14.0;
+        const ARROW_HALF_WIDTH: f64 = 6.0;
+        let direction = tip - tail;
+        let mut path = BezPath::new();
+        if direction.hypot() == 0.0 {
+            return path;
+        }
+        let direction = direction / direction.hypot();
+        let normal = Vec2::new(-direction.y, direction.x);
+        let base = tip - direction * ARROW_LENGTH * self.viewport.scale;
+        path.move_to(tip);
+        path.line_to(base + normal * ARROW_HALF_WIDTH * self.viewport.scale);
+        path.line_to(base - normal * ARROW_HALF_WIDTH * self.viewport.scale);
+        path.close_path();
+        path
+    }
+
+    fn paint_nodes(&self, ctx: &mut Piet, nodes: &[&DisplayNode], env: &Env) {
+        for n in nodes {
+            let transformed_rect = self.viewport.scene_rect_to_screen(n.rect);
+            let outline = shape::outline(n.shape, transformed_rect);
+            ctx.stroke_styled(&outline, &style::stroke(&n.style.line, theme::NODE_STROKE, env), style::stroke_width(&n.style.line, self.viewport.scale),
+                              &style::stroke_style(&n.style.line, self.viewport.scale));
+            ctx.fill(&outline, &style::fill(&n.style, env));
+            let text_layout = self.node_text_layout(ctx.text(), &n.text, &n.style, transformed_rect, env);
+            ctx.draw_text(&text_layout, self.node_text_origin(&text_layout, transformed_rect))
+        }
+    }
+
+    /// Marks pinned nodes with a dot in their top right corner
+    fn paint_pins(&self, ctx: &mut Piet, nodes: &[&DisplayNode], env: &Env) {
+        const PIN_RADIUS: f64 = 5.0;
+        for n in nodes.iter().filter(|n| self.pinned.contains(&n.id)) {
+            let corner = self.viewport.scene_coord_to_screen(Point::new(n.rect.x1, n.rect.y0));
+            ctx.fill(Circle::new(corner, PIN_RADIUS * self.viewport.scale), &env.get(theme::PIN));
+        }
     }
 
-    fn paint_edges(&self, ctx: &mut PaintCtx) {
-        for e in self.display_graph.edges().into_iter() {
-            let line = self.viewport.scene_line_to_screen(Line::new(e.start_point, e.end_point));
-            ctx.stroke(line, &Color::BLACK, self.viewport.line_weight());
+    fn paint_resize_handles(&self, ctx: &mut Piet, nodes: &[&DisplayNode], env: &Env) {
+        let (fill, stroke) = (env.get(theme::NODE_FILL), env.get(theme::HIGHLIGHT));
+        for n in nodes {
+            let screen_rect = self.viewport.scene_rect_to_screen(n.rect);
+            for handle in Handle::ALL {
+                let handle_rect = Rect::from_center_size(handle.position(screen_rect), (HANDLE_SIZE, HANDLE_SIZE));
+                ctx.fill(handle_rect, &fill);
+                ctx.stroke(handle_rect, &stroke, 1.5);
+            }
+        }
+    }
+
+    /// Resize handle of a visible selected node under the given screen position, if any
+    fn resize_handle_at(&self, screen_pos: Point, screen_size: Size) -> Option<(NodeId, Handle)> {
+        let visible_rect = self.viewport.visible_scene_rect(screen_size).inset(PAINT_MARGIN);
+        self.display_graph.nodes_intersecting(visible_rect).into_iter().rev()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .find_map(|n| {
+                let screen_rect = self.viewport.scene_rect_to_screen(n.rect);
+                Handle::at_point(screen_rect, screen_pos, HANDLE_SIZE / 2.0).map(|handle| (n.id, handle))
+            })
+    }
+
+    fn paint_text_edit(&self, ctx: &mut Piet, env: &Env) {
+        let edit = match &self.text_edit {
+            Some(edit) => edit,
+            None => return,
+        };
+        let node = self.display_graph.get_node(&edit.node_id).unwrap();
+        let screen_rect = self.viewport.scene_rect_to_screen(node.rect);
+        let text_layout = self.node_text_layout(ctx.text(), &edit.text, &node.style, screen_rect, env);
+        let text_origin = self.node_text_origin(&text_layout, screen_rect).to_vec2();
+        for selection_rect in text_layout.rects_for_range(edit.selection()) {
+            ctx.fill(selection_rect + text_origin, &env.get(theme::HIGHLIGHT).with_alpha(0.4));
         }
+        let caret_position = text_layout.hit_test_text_position(edit.caret());
+        if let Some(line_metric) = text_layout.line_metric(caret_position.line) {
+            let caret_x = caret_position.point.x;
+            let caret = Line::new((caret_x, line_metric.y_offset), (caret_x, line_metric.y_offset + line_metric.height));
+            ctx.stroke(caret + text_origin, &env.get(theme::NODE_TEXT), self.viewport.scale.max(1.0));
+        }
+    }
+
+    /// Shows the error from the last open or save in a banner along the bottom of the view
+    fn paint_error_message(&self, ctx: &mut Piet, size: Size, env: &Env) {
+        const MARGIN: f64 = 12.0;
+        const PADDING: f64 = 8.0;
+        let message = match &self.error_message {
+            Some(message) => message,
+            None => return,
+        };
+        let max_width = size.width - 2.0 * (MARGIN + PADDING);
+        let text_layout = ctx.text().new_text_layout(message.clone())
+            .font(FontFamily::default(), 14.0)
+            .text_color(env.get(theme::NODE_TEXT))
+            .max_width(max_width)
+            .build().unwrap();
+        let text_size = text_layout.size();
+        let text_origin = Point::new(MARGIN + PADDING, size.height - MARGIN - PADDING - text_size.height);
+        let banner = Rect::from_origin_size(text_origin, text_size).inflate(PADDING, PADDING).to_rounded_rect(4.0);
+        ctx.fill(banner, &env.get(theme::NODE_FILL));
+        ctx.stroke(banner, &env.get(theme::theme_color_key(ThemeColor::Red)), 2.0);
+        ctx.draw_text(&text_layout, text_origin);
     }
 
-    fn paint_nodes(&self, ctx: &mut PaintCtx) {
-        const DEFAULT_FONT_SIZE: f64 = 24.0;
-        for n in self.display_graph.nodes().into_iter() {
-            let transformed_rect = &self.viewport.scene_rect_to_screen(n.rect);
-            ctx.stroke(transformed_rect, &Color::BLACK, self.viewport.line_weight());
-            ctx.fill(transformed_rect, &Color::WHITE);
-            let text_layout = ctx.text().new_text_layout(n.text.clone())
-                .font(FontFamily::default(), DEFAULT_FONT_SIZE * self.viewport.scale)
-                .max_width(transformed_rect.width() - 8.0 * self.viewport.scale)
-                .alignment(TextAlignment::Center)
-                .build().unwrap();
-            let vertical_align_offset = transformed_rect.height() / 2.0 - text_layout.size().height / 2.0;
-            ctx.draw_text(&text_layout, Point::new(transformed_rect.x0, transformed_rect.y0 + vertical_align_offset))
+    /// Paints the view into an area of the given size. Only elements in view are painted when `cull` is set,
+    /// which keeps frame time independent of the graph's size - it's only unset to measure the difference.
+    pub(crate) fn paint_scene(&self, ctx: &mut Piet, size: Size, env: &Env, cull: bool) {
+        let highlight_color = env.get(theme::HIGHLIGHT);
+        ctx.fill(size.to_rect(), &env.get(theme::BACKGROUND));
+        self.paint_dot_grid(ctx, size, env);
+        self.paint_origin_marker(ctx, env);
+        let (mut visible_edges, mut visible_nodes) = if cull {
+            let visible_rect = self.viewport.visible_scene_rect(size).inset(PAINT_MARGIN);
+            (self.display_graph.edges_intersecting(visible_rect), self.display_graph.nodes_intersecting(visible_rect))
+        } else {
+            (self.display_graph.edges(), self.display_graph.nodes())
+        };
+        // Nodes being dragged and their edges are painted at their pending position, on top of the rest
+        let (dragged_nodes, dragged_edges) = match &self.drag_state {
+            Some(drag) if drag.offset != Vec2::ZERO => {
+                let node_ids: HashSet<NodeId> = drag.nodes_before.iter().map(|n| n.id).collect();
+                self.display_graph.translation_preview(&node_ids, drag.offset)
+            }
+            _ => (vec![], vec![]),
+        };
+        if !dragged_nodes.is_empty() {
+            let dragged_ids: HashSet<ElementId> = dragged_nodes.iter().map(|n| ElementId::Node(n.id))
+                .chain(dragged_edges.iter().map(|e| ElementId::Edge(e.id)))
+                .collect();
+            visible_nodes.retain(|n| !dragged_ids.contains(&ElementId::Node(n.id)));
+            visible_edges.retain(|e| !dragged_ids.contains(&ElementId::Edge(e.id)));
+            visible_nodes.extend(dragged_nodes.iter());
+            visible_edges.extend(dragged_edges.iter());
+        }
+        self.paint_edges(ctx, &visible_edges, env);
+        if let Some((_, line)) = self.new_edge {
+            ctx.stroke(self.viewport.scene_line_to_screen(line), &env.get(theme::EDGE_STROKE), self.viewport.line_weight());
+        }
+        self.paint_nodes(ctx, &visible_nodes, env);
+        self.paint_pins(ctx, &visible_nodes, env);
+        let selected_nodes: Vec<&DisplayNode> = visible_nodes.iter().copied()
+            .filter(|n| self.selection.contains(&ElementId::Node(n.id)))
+            .collect();
+        for selected_node in &selected_nodes {
+            ctx.stroke(shape::outline(selected_node.shape, self.viewport.scene_rect_to_screen(selected_node.rect)),
+                       &highlight_color, 3.0 * self.viewport.scale);
         }
+        for selected_edge in visible_edges.iter().filter(|e| self.selection.contains(&ElementId::Edge(e.id))) {
+            self.paint_edge(ctx, selected_edge, &highlight_color, 3.0 * self.viewport.scale, &StrokeStyle::new());
+        }
+        if let Some(drag) = &self.drag_state {
+            for guide_line in &drag.guide_lines {
+                ctx.stroke(self.viewport.scene_line_to_screen(*guide_line), &env.get(theme::GUIDE), 1.0);
+            }
+        }
+        self.paint_resize_handles(ctx, &selected_nodes, env);
+        self.paint_text_edit(ctx, env);
+        if let Some(marquee) = self.drag_state.as_ref().and_then(|d| d.marquee.as_ref()) {
+            let marquee_rect = self.viewport.scene_rect_to_screen(marquee.rect());
+            ctx.fill(marquee_rect, &highlight_color.clone().with_alpha(0.15));
+            if marquee.is_crossing() {
+                ctx.stroke_styled(marquee_rect, &highlight_color, 1.0, &StrokeStyle::new().dash(vec![4.0, 4.0], 0.0));
+            } else {
+                ctx.stroke(marquee_rect, &highlight_color, 1.0);
+            }
+        }
+        self.paint_error_message(ctx, size, env);
     }
 }
 
-impl Widget<()> for GraphView {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut (), _env: &Env) {
+/// The data is the theme in use, which the graph is painted with through the `Env`
+impl Widget<Theme> for GraphView {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Theme, env: &Env) {
         match event {
             Event::WindowConnected => ctx.request_focus(),
             Event::MouseDown(me) => {
-                let mut drag_state = DragState {
-                    buttons: me.buttons,
-                    last_mouse_pos: me.pos,
-                    has_moved: false,
-                    has_target: false,
-                };
+                if self.error_message.take().is_some() {
+                    ctx.request_paint();
+                }
+                self.finish_force_layout();
+                let mut drag_state = DragState::new(me.buttons, me.pos);
                 if me.button.is_left() {
-                    if me.count == 2 {
-                        self.display_graph.add_node(Node::new(self.viewport.screen_coord_to_scene(me.pos), None));
+                    let mouse_scene_pos = self.viewport.screen_coord_to_scene(me.pos);
+                    if self.is_over_text_edit(mouse_scene_pos) {
+                        // Clicking inside the node being edited moves the caret rather than the node
+                        let offset = self.text_edit_offset(ctx.text(), me.pos, env).unwrap();
+                        self.text_edit.as_mut().unwrap().set_caret(offset, me.mods.shift());
+                        drag_state.has_target = true;
+                        drag_state.is_text_selection = true;
+                        ctx.request_paint();
+                    } else if me.count == 2 {
+                        self.finish_text_edit();
+                        let node_id = match self.display_graph.get_node_at_point((mouse_scene_pos.x, mouse_scene_pos.y)) {
+                            Some(node) => node.id,
+                            None => {
+                                let mut node = Node::new(mouse_scene_pos, None);
+                                if !me.mods.alt() {
+                                    node.rect = node.rect + self.grid_snap.offset(node.rect);
+                                }
+                                let node_id = node.id;
+                                let index = self.display_graph.node_count();
+                                self.history.apply(&mut self.display_graph, vec![GraphEdit::AddNode { node, index }]);
+                                if self.auto_tidy {
+                                    self.tidy_overlaps(true);
+                                }
+                                node_id
+                            }
+                        };
+                        self.start_text_edit(node_id);
+                        drag_state.has_target = true;
                         ctx.request_paint();
+                    } else if let Some((node_id, handle)) = self.resize_handle_at(me.pos, ctx.size()) {
+                        self.finish_text_edit();
+                        drag_state.has_target = true;
+                        drag_state.resize_handle = Some(handle);
+                        drag_state.nodes_before = self.display_graph.get_node(&node_id).map(Node::from).into_iter().collect();
                     } else {
-                        let mouse_scene_pos = self.viewport.screen_coord_to_scene(me.pos);
-                        if let Some(node) = self.display_graph.get_mut_node_at_point((mouse_scene_pos.x, mouse_scene_pos.y)) {
+                        self.finish_text_edit();
+                        if let Some(node) = self.display_graph.get_node_at_point((mouse_scene_pos.x, mouse_scene_pos.y)) {
                             let node_selected = self.selection.contains(&ElementId::Node(node.id));
                             if !me.mods.ctrl() && !me.mods.shift() && !node_selected { self.selection.clear(); }
                             drag_state.has_target = true;
+                            drag_state.snap_anchor = Some(node.rect);
                             if me.mods.alt() {
                                 // Start new edge
                                 self.selection.clear();
@@ -117,6 +755,30 @@ impl Widget<()> for GraphView {
                             } else {
                                 self.selection.insert(ElementId::Node(node.id));
                             }
+                        } else if let Some(edge) = self.display_graph.get_edge_near_point(
+                            (mouse_scene_pos.x, mouse_scene_pos.y), EDGE_HIT_TOLERANCE / self.viewport.scale) {
+                            let edge_selected = self.selection.contains(&ElementId::Edge(edge.id));
+                            if !me.mods.ctrl() && !me.mods.shift() && !edge_selected { self.selection.clear(); }
+                            drag_state.has_target = true;
+                            self.selection.insert(ElementId::Edge(edge.id));
+                        } else if me.mods.alt() || me.mods.shift() || me.mods.ctrl() {
+                            // Modifier-drag on empty canvas draws a marquee rather than panning
+                            let mode = if me.mods.shift() {
+                                SelectionMode::Add
+                            } else if me.mods.ctrl() {
+                                SelectionMode::Toggle
+                            } else {
+                                SelectionMode::Replace
+                            };
+                            drag_state.marquee = Some(Marquee::new(mouse_scene_pos, mode, self.selection.clone()));
+                            drag_state.has_target = true;
+                        }
+                        if drag_state.has_target && self.new_edge.is_none() && drag_state.marquee.is_none() {
+                            // Snapshot the nodes about to be dragged so the whole drag is one undo step
+                            drag_state.nodes_before = self.selection.iter().filter_map(|elem_ref| match elem_ref {
+                                ElementId::Node(node_id) => self.display_graph.get_node(node_id).map(Node::from),
+                                ElementId::Edge(_) => None,
+                            }).collect();
                         }
                         ctx.request_paint();
                     }
@@ -128,118 +790,373 @@ impl Widget<()> for GraphView {
                     if !drag.has_target && !drag.has_moved {
                         self.selection.clear();
                         ctx.request_paint();
-                    } else if let Some((start_node_id, _)) = self.new_edge {
-                        let mouse_scene_pos = self.viewport.screen_coord_to_scene(me.pos);
-                        if let Some(end_node) = self.display_graph.get_node_at_point((mouse_scene_pos.x, mouse_scene_pos.y)) {
-                            self.display_graph.add_edge(Edge {
-                                id: EdgeId(Uuid::new_v4()),
-                                from_node_id: start_node_id,
-                                to_node_id: end_node.id,
-                            });
+                    } else if self.new_edge.is_some() {
+                        self.finish_new_edge(self.viewport.screen_coord_to_scene(me.pos));
+                        ctx.request_paint();
+                    }
+                }
+                if let Some(drag) = self.drag_state.take() {
+                    if drag.marquee.is_some() {
+                        ctx.request_paint();
+                    }
+                    if drag.offset != Vec2::ZERO {
+                        for before in &drag.nodes_before {
+                            self.display_graph.translate_node(&before.id, drag.offset);
                         }
-                        self.new_edge = None;
+                        ctx.request_paint();
+                    }
+                    let transaction = self.geometry_edits(drag.nodes_before);
+                    let resized = drag.resize_handle.is_some() && !transaction.is_empty();
+                    self.history.record(transaction);
+                    if resized && self.auto_tidy {
+                        self.tidy_overlaps(true);
                         ctx.request_paint();
                     }
                 }
-                self.drag_state = None
             }
             Event::MouseMove(me) => {
-                match &mut self.drag_state {
-                    Some(drag_state) => {
-                        let mouse_move = drag_state.last_mouse_pos - me.pos;
-                        if drag_state.buttons.has_left() && !drag_state.has_target {
-                            self.viewport.apply_mouse_move(mouse_move);
+                let text_selection_offset = match &self.drag_state {
+                    Some(drag_state) if drag_state.is_text_selection => self.text_edit_offset(ctx.text(), me.pos, env),
+                    _ => None,
+                };
+                if let Some(drag_state) = &mut self.drag_state {
+                    let mouse_move = drag_state.last_mouse_pos - me.pos;
+                    if drag_state.buttons.has_left() && !drag_state.has_target {
+                        self.viewport.apply_mouse_move(mouse_move);
+                        ctx.request_paint();
+                    } else if drag_state.is_text_selection {
+                        if let (Some(edit), Some(offset)) = (&mut self.text_edit, text_selection_offset) {
+                            edit.set_caret(offset, true);
                             ctx.request_paint();
-                        } else if drag_state.has_target {
-                            if let Some((_, ref mut line)) = &mut self.new_edge {
-                                line.p1 = self.viewport.screen_coord_to_scene(me.pos);
-                                ctx.request_paint();
+                        }
+                    } else if let Some(marquee) = &mut drag_state.marquee {
+                        marquee.end = self.viewport.screen_coord_to_scene(me.pos);
+                        self.selection = marquee.selection(&self.display_graph);
+                        ctx.request_paint();
+                    } else if drag_state.has_target {
+                        if let Some((_, ref mut line)) = &mut self.new_edge {
+                            line.p1 = self.viewport.screen_coord_to_scene(me.pos);
+                            ctx.request_paint();
+                        } else if let Some(handle) = drag_state.resize_handle {
+                            drag_state.mouse_offset -= mouse_move / self.viewport.scale;
+                            // Holding Shift keeps the node's proportions
+                            let before = &drag_state.nodes_before[0];
+                            let rect = resize(before.rect, handle, drag_state.mouse_offset, me.mods.shift());
+                            // Auto-sized nodes still fit their text at the new size, from the side that isn't being dragged
+                            let rect = auto_size::fit(ctx.text(), &before.text, &before.style, before.auto_size, rect, Some(handle));
+                            self.display_graph.set_node_rect(&before.id, rect);
+                            ctx.request_paint();
+                        } else if !drag_state.nodes_before.is_empty() {
+                            drag_state.mouse_offset -= mouse_move / self.viewport.scale;
+                            drag_state.offset = drag_state.mouse_offset;
+                            let dragged_bounds = drag_state.nodes_before.iter().skip(1)
+                                .fold(drag_state.nodes_before[0].rect, |bounds, n| bounds.union(n.rect));
+                            let guides = drag_state.guides.get_or_insert_with(|| {
+                                let dragged = drag_state.nodes_before.iter().map(|n| n.id).collect();
+                                Guides::new(&self.display_graph, dragged_bounds, &dragged)
+                            });
+                            // Holding Alt moves nodes freely even when snapping is on
+                            if !me.mods.alt() {
+                                // Lining up with other nodes takes priority over the grid
+                                let anchor = drag_state.snap_anchor.unwrap_or(drag_state.nodes_before[0].rect);
+                                let grid_offset = self.grid_snap.offset(anchor + drag_state.mouse_offset);
+                                let (guide_x, guide_y) = guides.snap(dragged_bounds + drag_state.mouse_offset,
+                                                                     GUIDE_SNAP_DISTANCE / self.viewport.scale);
+                                drag_state.offset += Vec2::new(guide_x.unwrap_or(grid_offset.x), guide_y.unwrap_or(grid_offset.y));
+                                drag_state.guide_lines = guides.lines(dragged_bounds + drag_state.offset);
                             } else {
-                                for elem_ref in &self.selection {
-                                    match elem_ref {
-                                        ElementId::Node(node_id) => {
-                                            /* TODO: Easy optimisation here would be to avoid having to update the data structures
-                                                on every single mouse move. Instead, could simply filter out actual nodes from
-                                                normal paint and paint the nodes being dragged separately. */
-                                            self.display_graph.translate_node(node_id, -mouse_move / self.viewport.scale);
-                                        }
-                                        ElementId::Edge(_) => {}
-                                    }
-                                }
-                                ctx.request_paint();
+                                drag_state.guide_lines.clear();
                             }
+                            ctx.request_paint();
                         }
-                        drag_state.has_moved = drag_state.last_mouse_pos != me.pos;
-                        drag_state.last_mouse_pos = me.pos;
-                        drag_state.buttons = me.buttons;
                     }
-                    None => {}
+                    drag_state.has_moved = drag_state.last_mouse_pos != me.pos;
+                    drag_state.last_mouse_pos = me.pos;
+                    drag_state.buttons = me.buttons;
+                }
+            }
+            Event::AnimFrame(_) if self.force_animation.is_some() => {
+                if self.step_force_layout() {
+                    self.finish_force_layout();
+                } else {
+                    ctx.request_anim_frame();
                 }
+                ctx.request_paint();
             }
             Event::Wheel(me) => {
                 self.viewport.apply_scale(me.pos, -me.wheel_delta.y / 1600.0);
                 ctx.request_paint();
             }
             Event::Zoom(scale_amount) => {
-                self.viewport.apply_scale((ctx.size() / 2.0).to_vec2().to_point(), scale_amount.clone());
+                self.viewport.apply_scale((ctx.size() / 2.0).to_vec2().to_point(), *scale_amount);
+            }
+            Event::Command(cmd) => {
+                if let Some(file_info) = cmd.get(commands::OPEN_FILE) {
+                    self.open_document(file_info.path());
+                    ctx.request_paint();
+                } else if let Some(file_info) = cmd.get(commands::SAVE_FILE_AS) {
+                    self.save_document(file_info.path());
+                    ctx.request_paint();
+                } else if let Some(message) = cmd.get(SHOW_ERROR) {
+                    self.error_message = Some(message.clone());
+                    ctx.request_paint();
+                }
             }
             Event::KeyDown(ke) => {
-                let maybe_graph =
-                    if HotKey::new(Some(RawMods::Shift), KbKey::Escape).matches(ke) {
-                        Some(DisplayGraph::default())
-                    } else if HotKey::new(Some(RawMods::AltShift), "A").matches(ke) {
-                        Some(example_graphs::arborealis_graph())
-                    } else { None };
-                if let Some(graph) = maybe_graph {
-                    self.display_graph = graph;
-                    self.selection.clear();
+                if self.error_message.take().is_some() {
+                    ctx.request_paint();
+                }
+                let edit_outcome = match &mut self.text_edit {
+                    Some(edit) => edit.key_down(ke),
+                    None => EditOutcome::Ignored,
+                };
+                match edit_outcome {
+                    EditOutcome::Handled => {
+                        let edit = self.text_edit.as_ref().unwrap();
+                        self.display_graph.set_node_text(&edit.node_id, edit.text.clone());
+                        let node = self.display_graph.get_node(&edit.node_id).unwrap();
+                        let rect = auto_size::fit(ctx.text(), &node.text, &node.style, node.auto_size, node.rect, None);
+                        self.display_graph.set_node_rect(&edit.node_id, rect);
+                    }
+                    EditOutcome::Finished => self.finish_text_edit(),
+                    EditOutcome::Ignored => {}
+                }
+                if !matches!(edit_outcome, EditOutcome::Ignored) {
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, KbKey::Enter).matches(ke) && self.selection.len() == 1 {
+                    if let Some(ElementId::Node(node_id)) = self.selection.iter().next().copied() {
+                        self.start_text_edit(node_id);
+                        ctx.set_handled();
+                        ctx.request_paint();
+                    }
+                } else if HotKey::new(None, KbKey::Delete).matches(ke) || HotKey::new(None, KbKey::Backspace).matches(ke) {
+                    self.delete_selection();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "e").matches(ke) {
+                    self.cycle_selected_edge_types();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if let Some(command) = [
+                    (None, "c", StyleCommand::CycleFill),
+                    (Some(RawMods::Shift), "C", StyleCommand::CycleStroke),
+                    (None, "w", StyleCommand::CycleStrokeWidth),
+                    (None, "d", StyleCommand::CycleDash),
+                    (None, "m", StyleCommand::CycleFontFamily),
+                    (None, "b", StyleCommand::ToggleBold),
+                    (None, "]", StyleCommand::GrowFont),
+                    (None, "[", StyleCommand::ShrinkFont),
+                    (None, "j", StyleCommand::CycleTextAlignment),
+                ].into_iter().find(|(mods, key, _)| HotKey::new(*mods, *key).matches(ke)).map(|(_, _, command)| command) {
+                    self.restyle_selection(ctx.text(), command);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "n").matches(ke) {
+                    self.cycle_selected_shapes();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "a").matches(ke) {
+                    self.cycle_selected_auto_sizes(ctx.text());
                     ctx.set_handled();
                     ctx.request_paint();
+                } else if HotKey::new(Some(RawMods::Alt), "d").matches(ke) {
+                    *data = data.next();
+                    ctx.set_handled();
+                } else if HotKey::new(None, "t").matches(ke) {
+                    self.layout_selected_tree(TreeDirection::LeftToRight);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(Some(RawMods::Shift), "T").matches(ke) {
+                    self.layout_selected_tree(TreeDirection::TopDown);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(Some(RawMods::Alt), "t").matches(ke) {
+                    self.layout_selected_tree(TreeDirection::Balanced);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "l").matches(ke) {
+                    self.layout_layers(LayerDirection::TopDown);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(Some(RawMods::Shift), "L").matches(ke) {
+                    self.layout_layers(LayerDirection::LeftToRight);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "r").matches(ke) {
+                    self.layout_radially();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "o").matches(ke) {
+                    self.tidy_overlaps(false);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(Some(RawMods::Shift), "O").matches(ke) {
+                    self.auto_tidy = !self.auto_tidy;
+                    if self.auto_tidy {
+                        self.tidy_overlaps(false);
+                    }
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "g").matches(ke) {
+                    self.grid_snap.enabled = !self.grid_snap.enabled;
+                    ctx.set_handled();
+                } else if HotKey::new(Some(RawMods::Shift), "G").matches(ke) {
+                    self.grid_snap.target = match self.grid_snap.target {
+                        SnapTarget::Origin => SnapTarget::Center,
+                        SnapTarget::Center => SnapTarget::Origin,
+                    };
+                    ctx.set_handled();
+                } else if let Some(alignment) = [
+                    (RawMods::Alt, KbKey::ArrowLeft, Alignment::Left),
+                    (RawMods::Alt, KbKey::ArrowRight, Alignment::Right),
+                    (RawMods::Alt, KbKey::ArrowUp, Alignment::Top),
+                    (RawMods::Alt, KbKey::ArrowDown, Alignment::Bottom),
+                    (RawMods::AltShift, KbKey::ArrowLeft, Alignment::HorizontalCenter),
+                    (RawMods::AltShift, KbKey::ArrowRight, Alignment::HorizontalCenter),
+                    (RawMods::AltShift, KbKey::ArrowUp, Alignment::VerticalCenter),
+                    (RawMods::AltShift, KbKey::ArrowDown, Alignment::VerticalCenter),
+                ].into_iter().find(|(mods, key, _)| HotKey::new(Some(*mods), key.clone()).matches(ke)).map(|(_, _, a)| a) {
+                    self.arrange_selection(|nodes| arrange::align(nodes, alignment));
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "h").matches(ke) {
+                    self.arrange_selection(|nodes| arrange::distribute(nodes, Axis::Horizontal));
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "v").matches(ke) {
+                    self.arrange_selection(|nodes| arrange::distribute(nodes, Axis::Vertical));
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "=").matches(ke) {
+                    self.arrange_selection(arrange::make_same_size);
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "f").matches(ke) {
+                    if self.force_animation.is_some() {
+                        self.finish_force_layout();
+                    } else {
+                        self.start_force_layout();
+                        ctx.request_anim_frame();
+                    }
+                    ctx.set_handled();
+                } else if HotKey::new(Some(RawMods::Shift), "F").matches(ke) {
+                    self.run_force_layout_to_convergence();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(None, "p").matches(ke) {
+                    self.toggle_selected_pins();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(SysMods::Cmd, "z").matches(ke) {
+                    self.undo();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(SysMods::CmdShift, "Z").matches(ke) || HotKey::new(SysMods::Cmd, "y").matches(ke) {
+                    self.redo();
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(SysMods::Cmd, "o").matches(ke) {
+                    ctx.submit_command(commands::SHOW_OPEN_PANEL.with(GraphView::document_dialog_options()));
+                    ctx.set_handled();
+                } else if HotKey::new(SysMods::Cmd, "s").matches(ke) {
+                    match self.document_path.clone() {
+                        Some(path) => self.save_document(&path),
+                        None => ctx.submit_command(commands::SHOW_SAVE_PANEL.with(GraphView::document_dialog_options())),
+                    }
+                    ctx.set_handled();
+                    ctx.request_paint();
+                } else if HotKey::new(SysMods::CmdShift, "S").matches(ke) {
+                    ctx.submit_command(commands::SHOW_SAVE_PANEL.with(GraphView::document_dialog_options()));
+                    ctx.set_handled();
+                } else {
+                    let maybe_graph =
+                        if HotKey::new(Some(RawMods::Shift), KbKey::Escape).matches(ke) {
+                            Some(DisplayGraph::default())
+                        } else if HotKey::new(Some(RawMods::AltShift), "A").matches(ke) {
+                            Some(example_graphs::arborealis_graph())
+                        } else { None };
+                    if let Some(graph) = maybe_graph {
+                        self.set_display_graph(graph, None);
+                        ctx.set_handled();
+                        ctx.request_paint();
+                    }
                 }
             }
             _ => ()
         }
     }
 
-    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &(), _env: &Env) {
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &Theme, _env: &Env) {
         ctx.register_for_focus();
     }
 
-    fn update(&mut self, _ctx: &mut UpdateCtx, _old_data: &(), _data: &(), _env: &Env) {}
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Theme, data: &Theme, _env: &Env) {
+        if !old_data.same(data) {
+            ctx.request_paint();
+        }
+    }
 
-    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &(), _env: &Env) -> Size {
+    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &Theme, _env: &Env) -> Size {
         Size {
             width: (if bc.is_width_bounded() { bc.max().width } else { 100.0 }),
             height: (if bc.is_height_bounded() { bc.max().height } else { 100.0 }),
         }
     }
 
-    fn paint(&mut self, ctx: &mut PaintCtx, _data: &(), _env: &Env) {
-        let start_time = Instant::now();
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Theme, env: &Env) {
+        let size = ctx.size();
+        self.paint_scene(ctx.render_ctx, size, env, true);
+    }
+}
 
-        const BG_COLOR: Color = Color::grey8(0xf0);
-        const HIGHLIGHT_COLOR: Color = Color::rgb8(0x75, 0xa7, 0xf8);
-        let paint_area = ctx.size().to_rect();
-        ctx.fill(paint_area, &BG_COLOR);
-        self.paint_dot_grid(ctx);
-        self.paint_origin_marker(ctx);
-        self.paint_edges(ctx);
-        if let Some((_, line)) = self.new_edge {
-            ctx.stroke(self.viewport.scene_line_to_screen(line), &Color::BLACK, self.viewport.line_weight());
-        }
-        self.paint_nodes(ctx);
-        for elem_ref in &self.selection {
-            match elem_ref {
-                ElementId::Node(node_id) => {
-                    let selected_node = self.display_graph.get_node(&node_id).unwrap();
-                    ctx.stroke(self.viewport.scene_rect_to_screen(selected_node.rect),
-                               &HIGHLIGHT_COLOR, 3.0 * self.viewport.scale);
-                }
-                ElementId::Edge(_edge_id) => {}
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undoing_a_force_layout_keeps_edits_made_while_it_ran() {
+        let a = Node::new(Point::new(0.0, 0.0), None);
+        let b = Node::new(Point::new(10.0, 0.0), None);
+        let graph = Graph { nodes: vec![a.clone(), b.clone()], edges: vec![Edge::new(a.id, b.id, EdgeType::Directional)] };
+        let mut view = GraphView::new();
+        view.set_display_graph(DisplayGraph::from(&graph), None);
+
+        view.start_force_layout();
+        view.step_force_layout();
+        view.selection.insert(ElementId::Node(a.id));
+        view.cycle_selected_shapes();
+        let laid_out = Node::from(view.display_graph.get_node(&a.id).unwrap());
+        assert_ne!(laid_out.rect, a.rect);
+        assert_eq!(laid_out.shape, a.shape.next());
+
+        view.undo();
+        assert_eq!(Node::from(view.display_graph.get_node(&a.id).unwrap()), Node { shape: a.shape, ..laid_out.clone() });
+        view.redo();
+        view.undo();
+        view.undo();
+        assert_eq!(Graph::from(&view.display_graph), graph);
+    }
+
+    #[test]
+    fn undoing_the_start_of_a_new_edge_mid_drag_abandons_the_edge() {
+        let target = Node::new(Point::new(200.0, 0.0), None);
+        let mut view = GraphView::new();
+        view.set_display_graph(DisplayGraph::from(&Graph { nodes: vec![target.clone()], edges: vec![] }), None);
+        let start = Node::new(Point::ZERO, None);
+        view.history.apply(&mut view.display_graph, vec![GraphEdit::AddNode { node: start.clone(), index: 1 }]);
 
-        let paint_time = Instant::now() - start_time;
-        println!("Time to paint: {:.3}ms, equivalent FPS: {}", paint_time.as_secs_f64() * 1000.0, (1.0 / paint_time.as_secs_f64()).round());
+        let mut drag_state = DragState::new(MouseButtons::new().with(MouseButton::Left), Point::ZERO);
+        drag_state.has_target = true;
+        view.drag_state = Some(drag_state);
+        view.new_edge = Some((start.id, Line::new(start.rect.center(), target.rect.center())));
+        view.undo();
+        assert!(view.drag_state.is_none());
+        assert!(view.new_edge.is_none());
+
+        // Even if the gesture outlived the node, releasing over another one mustn't connect to it
+        view.new_edge = Some((start.id, Line::new(start.rect.center(), target.rect.center())));
+        view.finish_new_edge(target.rect.center());
+        assert_eq!(view.display_graph.edge_count(), 0);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,122 @@
+use druid::{Point, Rect, Size, Vec2};
+
+/// Smallest size a node can be resized to, in scene units
+pub(crate) const MIN_NODE_SIZE: Size = Size { width: 40.0, height: 24.0 };
+
+/// One of the squares drawn around a selected node that can be dragged to resize it
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Handle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+}
+
+impl Handle {
+    pub(crate) const ALL: [Handle; 8] = [
+        Handle::TopLeft, Handle::Top, Handle::TopRight, Handle::Right,
+        Handle::BottomRight, Handle::Bottom, Handle::BottomLeft, Handle::Left,
+    ];
+
+    /// Which way the handle moves the rect's sides along each axis: -1 for the left or top side,
+    /// 1 for the right or bottom side and 0 when it leaves that axis alone
+    fn direction(self) -> (i8, i8) {
+        match self {
+            Handle::TopLeft => (-1, -1),
+            Handle::Top => (0, -1),
+            Handle::TopRight => (1, -1),
+            Handle::Right => (1, 0),
+            Handle::BottomRight => (1, 1),
+            Handle::Bottom => (0, 1),
+            Handle::BottomLeft => (-1, 1),
+            Handle::Left => (-1, 0),
+        }
+    }
+
+    pub(crate) fn position(self, rect: Rect) -> Point {
+        let (dx, dy) = self.direction();
+        let center = rect.center();
+        Point::new(center.x + dx as f64 * rect.width() / 2.0, center.y + dy as f64 * rect.height() / 2.0)
+    }
+
+    /// Handle of `rect` within `tolerance` of `point` on both axes, if any
+    pub(crate) fn at_point(rect: Rect, point: Point, tolerance: f64) -> Option<Handle> {
+        Handle::ALL.into_iter().find(|handle| {
+            let position = handle.position(rect);
+            (position.x - point.x).abs() <= tolerance && (position.y - point.y).abs() <= tolerance
+        })
+    }
+}
+
+/// `rect` with the sides under `handle` moved by `offset`, keeping the opposite sides in place.
+/// Axes the handle doesn't move stay centred, except when `keep_aspect` scales them along with the rest.
+/// The result is never smaller than `MIN_NODE_SIZE`.
+pub(crate) fn resize(rect: Rect, handle: Handle, offset: Vec2, keep_aspect: bool) -> Rect {
+    let (dx, dy) = handle.direction();
+    let mut width = (rect.width() + dx as f64 * offset.x).max(MIN_NODE_SIZE.width);
+    let mut height = (rect.height() + dy as f64 * offset.y).max(MIN_NODE_SIZE.height);
+    if keep_aspect && !rect.size().is_empty() {
+        let scale = match (dx, dy) {
+            (0, _) => height / rect.height(),
+            (_, 0) => width / rect.width(),
+            _ => (width / rect.width()).max(height / rect.height()),
+        };
+        let scale = scale.max(MIN_NODE_SIZE.width / rect.width()).max(MIN_NODE_SIZE.height / rect.height());
+        width = rect.width() * scale;
+        height = rect.height() * scale;
+    }
+    anchored_resize(rect, handle, Size::new(width, height))
+}
+
+/// `rect` resized to `size` with the sides opposite `handle` kept in place. Axes the handle doesn't move stay centred.
+pub(crate) fn anchored_resize(rect: Rect, handle: Handle, size: Size) -> Rect {
+    let (dx, dy) = handle.direction();
+    let center = rect.center();
+    let x0 = match dx {
+        -1 => rect.x1 - size.width,
+        1 => rect.x0,
+        _ => center.x - size.width / 2.0,
+    };
+    let y0 = match dy {
+        -1 => rect.y1 - size.height,
+        1 => rect.y0,
+        _ => center.y - size.height / 2.0,
+    };
+    Rect::from_origin_size((x0, y0), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect { x0: 0.0, y0: 0.0, x1: 100.0, y1: 60.0 };
+
+    #[test]
+    fn handles_move_their_sides_and_keep_the_opposite_ones() {
+        assert_eq!(resize(RECT, Handle::BottomRight, Vec2::new(20.0, 10.0), false), Rect::new(0.0, 0.0, 120.0, 70.0));
+        assert_eq!(resize(RECT, Handle::TopLeft, Vec2::new(20.0, 10.0), false), Rect::new(20.0, 10.0, 100.0, 60.0));
+        assert_eq!(resize(RECT, Handle::Left, Vec2::new(-50.0, 30.0), false), Rect::new(-50.0, 0.0, 100.0, 60.0));
+        assert_eq!(Handle::at_point(RECT, Point::new(98.0, 31.0), 4.0), Some(Handle::Right));
+        assert_eq!(Handle::at_point(RECT, Point::new(50.0, 20.0), 4.0), None);
+    }
+
+    #[test]
+    fn aspect_lock_scales_both_sides() {
+        assert_eq!(resize(RECT, Handle::BottomRight, Vec2::new(100.0, 0.0), true), Rect::new(0.0, 0.0, 200.0, 120.0));
+        // Edge handles grow the other axis around its centre
+        assert_eq!(resize(RECT, Handle::Bottom, Vec2::new(0.0, 60.0), true), Rect::new(-50.0, 0.0, 150.0, 120.0));
+    }
+
+    #[test]
+    fn rects_stop_at_the_minimum_size() {
+        let shrunk = resize(RECT, Handle::TopLeft, Vec2::new(500.0, 500.0), false);
+        assert_eq!(shrunk, Rect::new(100.0 - MIN_NODE_SIZE.width, 60.0 - MIN_NODE_SIZE.height, 100.0, 60.0));
+        let shrunk = resize(RECT, Handle::BottomRight, Vec2::new(-500.0, -500.0), true);
+        assert!(shrunk.width() >= MIN_NODE_SIZE.width && shrunk.height() >= MIN_NODE_SIZE.height);
+        assert!((shrunk.width() / shrunk.height() - RECT.width() / RECT.height()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,213 @@
+use druid::{Color, Env, FontFamily, FontWeight, Key, TextAlignment};
+use druid::piet::{PietText, PietTextLayoutBuilder, StrokeStyle, Text, TextLayoutBuilder};
+
+use crate::graph::style::{LineStyle, NodeStyle, StyleColor, TextAlign, ThemeColor};
+use crate::graph_view::DEFAULT_FONT_SIZE;
+use crate::theme;
+
+/// Width of edges and node borders that don't set one, in scene units
+const DEFAULT_STROKE_WIDTH: f64 = 2.0;
+const BOLD_WEIGHT: u16 = 700;
+
+/// Options the style commands cycle through, each starting with the default.
+/// Colours are theme colours so that they suit whichever theme is in use.
+const FILLS: [Option<StyleColor>; 6] = [
+    None,
+    Some(StyleColor::Theme(ThemeColor::YellowTint)),
+    Some(StyleColor::Theme(ThemeColor::GreenTint)),
+    Some(StyleColor::Theme(ThemeColor::BlueTint)),
+    Some(StyleColor::Theme(ThemeColor::RedTint)),
+    Some(StyleColor::Theme(ThemeColor::MutedTint)),
+];
+const STROKES: [Option<StyleColor>; 5] = [
+    None,
+    Some(StyleColor::Theme(ThemeColor::Red)),
+    Some(StyleColor::Theme(ThemeColor::Green)),
+    Some(StyleColor::Theme(ThemeColor::Blue)),
+    Some(StyleColor::Theme(ThemeColor::Muted)),
+];
+const STROKE_WIDTHS: [Option<f64>; 4] = [None, Some(1.0), Some(4.0), Some(6.0)];
+const DASHES: [Option<&[f64]>; 3] = [None, Some(&[8.0, 6.0]), Some(&[2.0, 4.0])];
+const FONT_FAMILIES: [Option<&str>; 4] = [None, Some("serif"), Some("sans-serif"), Some("monospace")];
+const TEXT_ALIGNMENTS: [Option<TextAlign>; 3] = [None, Some(TextAlign::Start), Some(TextAlign::End)];
+/// How much the font size commands change the size by, and the range they stay within, in scene units
+const FONT_SIZE_STEP: f64 = 4.0;
+const FONT_SIZE_RANGE: (f64, f64) = (8.0, 96.0);
+
+/// Ways of restyling the selection from the keyboard
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum StyleCommand {
+    CycleFill,
+    CycleStroke,
+    CycleStrokeWidth,
+    CycleDash,
+    CycleFontFamily,
+    ToggleBold,
+    GrowFont,
+    ShrinkFont,
+    CycleTextAlignment,
+}
+
+/// A single style property set to a new value
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum StyleChange {
+    Fill(Option<StyleColor>),
+    Stroke(Option<StyleColor>),
+    StrokeWidth(Option<f64>),
+    Dash(Option<Vec<f64>>),
+    FontFamily(Option<String>),
+    FontSize(Option<f64>),
+    FontWeight(Option<u16>),
+    TextAlignment(Option<TextAlign>),
+}
+
+impl StyleCommand {
+    /// The change this command makes to an element styled `current`. The whole selection gets the same change,
+    /// worked out from its first element, so that a selection with mixed styles ends up consistent.
+    pub(crate) fn change(self, current: &NodeStyle) -> StyleChange {
+        let font_size = current.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+        match self {
+            StyleCommand::CycleFill => StyleChange::Fill(next_option(&FILLS, current.fill)),
+            StyleCommand::CycleStroke => StyleChange::Stroke(next_option(&STROKES, current.line.stroke)),
+            StyleCommand::CycleStrokeWidth => StyleChange::StrokeWidth(next_option(&STROKE_WIDTHS, current.line.stroke_width)),
+            StyleCommand::CycleDash =>
+                StyleChange::Dash(next_option(&DASHES, current.line.dash.as_deref()).map(<[f64]>::to_vec)),
+            StyleCommand::CycleFontFamily =>
+                StyleChange::FontFamily(next_option(&FONT_FAMILIES, current.font_family.as_deref()).map(String::from)),
+            StyleCommand::ToggleBold => StyleChange::FontWeight(match current.font_weight {
+                Some(weight) if weight >= BOLD_WEIGHT => None,
+                _ => Some(BOLD_WEIGHT),
+            }),
+            StyleCommand::GrowFont =>
+                StyleChange::FontSize(Some((font_size + FONT_SIZE_STEP).clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1))),
+            StyleCommand::ShrinkFont =>
+                StyleChange::FontSize(Some((font_size - FONT_SIZE_STEP).clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1))),
+            StyleCommand::CycleTextAlignment => StyleChange::TextAlignment(next_option(&TEXT_ALIGNMENTS, current.text_alignment)),
+        }
+    }
+}
+
+impl StyleChange {
+    pub(crate) fn apply_to_node(&self, style: &mut NodeStyle) {
+        match self {
+            StyleChange::Fill(fill) => style.fill = *fill,
+            StyleChange::FontFamily(font_family) => style.font_family = font_family.clone(),
+            StyleChange::FontSize(font_size) => style.font_size = *font_size,
+            StyleChange::FontWeight(font_weight) => style.font_weight = *font_weight,
+            StyleChange::TextAlignment(text_alignment) => style.text_alignment = *text_alignment,
+            StyleChange::Stroke(_) | StyleChange::StrokeWidth(_) | StyleChange::Dash(_) => self.apply_to_line(&mut style.line),
+        }
+    }
+
+    /// Applies the change to an edge or border, which ignores changes to fill and text
+    pub(crate) fn apply_to_line(&self, style: &mut LineStyle) {
+        match self {
+            StyleChange::Stroke(stroke) => style.stroke = *stroke,
+            StyleChange::StrokeWidth(stroke_width) => style.stroke_width = *stroke_width,
+            StyleChange::Dash(dash) => style.dash = dash.clone(),
+            _ => {}
+        }
+    }
+}
+
+/// The option after `current`, wrapping around. Values that aren't one of the options go back to the first one.
+fn next_option<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let index = options.iter().position(|option| *option == current).map_or(0, |i| i + 1);
+    options[index % options.len()]
+}
+
+/// A style's colour, looking theme colours up in the `Env`, or the theme's `default` when it's unset
+fn color(color: Option<StyleColor>, default: Key<Color>, env: &Env) -> Color {
+    match color {
+        Some(StyleColor::Rgba(rgba)) => rgba.into(),
+        Some(StyleColor::Theme(theme_color)) => env.get(theme::theme_color_key(theme_color)),
+        None => env.get(default),
+    }
+}
+
+pub(crate) fn fill(style: &NodeStyle, env: &Env) -> Color {
+    color(style.fill, theme::NODE_FILL, env)
+}
+
+/// Colour of a node's border, or of an edge when `default` is the edge colour
+pub(crate) fn stroke(style: &LineStyle, default: Key<Color>, env: &Env) -> Color {
+    color(style.stroke, default, env)
+}
+
+/// Line width on screen at the given viewport scale
+pub(crate) fn stroke_width(style: &LineStyle, scale: f64) -> f64 {
+    style.stroke_width.unwrap_or(DEFAULT_STROKE_WIDTH) * scale
+}
+
+/// Dash pattern on screen at the given viewport scale
+pub(crate) fn stroke_style(style: &LineStyle, scale: f64) -> StrokeStyle {
+    match &style.dash {
+        Some(dash) => StrokeStyle::new().dash(dash.iter().map(|length| length * scale).collect(), 0.0),
+        None => StrokeStyle::new(),
+    }
+}
+
+fn font_family(name: Option<&str>) -> FontFamily {
+    match name {
+        None => FontFamily::default(),
+        Some("serif") => FontFamily::SERIF,
+        Some("sans-serif") => FontFamily::SANS_SERIF,
+        Some("monospace") => FontFamily::MONOSPACE,
+        Some("system-ui") => FontFamily::SYSTEM_UI,
+        Some(name) => FontFamily::new_unchecked(name),
+    }
+}
+
+fn text_alignment(alignment: Option<TextAlign>) -> TextAlignment {
+    match alignment {
+        Some(TextAlign::Start) => TextAlignment::Start,
+        Some(TextAlign::End) => TextAlignment::End,
+        Some(TextAlign::Center) | None => TextAlignment::Center,
+    }
+}
+
+/// Starts laying out a node's text in its font, wrapped at `max_width`, with sizes multiplied by `scale`
+pub(crate) fn node_text_layout(text_factory: &mut PietText, text: &str, style: &NodeStyle, scale: f64, max_width: f64) -> PietTextLayoutBuilder {
+    text_factory.new_text_layout(text.to_string())
+        .font(font_family(style.font_family.as_deref()), style.font_size.unwrap_or(DEFAULT_FONT_SIZE) * scale)
+        .default_attribute(FontWeight::new(style.font_weight.unwrap_or(FontWeight::REGULAR.to_raw())))
+        .max_width(max_width)
+        .alignment(text_alignment(style.text_alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::style::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn commands_cycle_from_the_current_style() {
+        let mut style = NodeStyle::default();
+        for _ in 0..FILLS.len() {
+            StyleCommand::CycleFill.change(&style).apply_to_node(&mut style);
+        }
+        assert_eq!(style.fill, None);
+        StyleCommand::CycleDash.change(&style).apply_to_node(&mut style);
+        assert_eq!(style.line.dash, Some(vec![8.0, 6.0]));
+        style.fill = Some(StyleColor::Rgba(Rgba(0x12345678)));
+        assert_eq!(StyleCommand::CycleFill.change(&style), StyleChange::Fill(None));
+
+        StyleCommand::ToggleBold.change(&style).apply_to_node(&mut style);
+        assert_eq!(style.font_weight, Some(BOLD_WEIGHT));
+        StyleCommand::ToggleBold.change(&style).apply_to_node(&mut style);
+        assert_eq!(style.font_weight, None);
+        style.font_size = Some(FONT_SIZE_RANGE.1 - 1.0);
+        assert_eq!(StyleCommand::GrowFont.change(&style), StyleChange::FontSize(Some(FONT_SIZE_RANGE.1)));
+    }
+
+    #[test]
+    fn lines_only_take_line_changes() {
+        let mut line = LineStyle::default();
+        StyleChange::Fill(Some(StyleColor::Theme(ThemeColor::Red))).apply_to_line(&mut line);
+        StyleChange::FontSize(Some(12.0)).apply_to_line(&mut line);
+        assert_eq!(line, LineStyle::default());
+        StyleChange::StrokeWidth(Some(4.0)).apply_to_line(&mut line);
+        assert_eq!(line.stroke_width, Some(4.0));
+    }
+}
@@ -0,0 +1,14 @@
+//! Fixtures shared by the tests of the view's modules
+use druid::{Point, Rect, Size};
+
+use crate::graph::edge::{Edge, EdgeType};
+use crate::graph::node::Node;
+
+/// Node with default properties whose rect has the given origin and size
+pub(crate) fn node(origin: (f64, f64), size: (f64, f64)) -> Node {
+    Node { rect: Rect::from_origin_size(origin, Size::new(size.0, size.1)), ..Node::new(Point::ZERO, None) }
+}
+
+pub(crate) fn edge(from: &Node, to: &Node) -> Edge {
+    Edge::new(from.id, to.id, EdgeType::Directional)
+}
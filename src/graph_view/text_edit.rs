@@ -0,0 +1,212 @@
+use std::ops::Range;
+
+use druid::{Application, HotKey, KbKey, KeyEvent, SysMods};
+
+use crate::graph::node::{Node, NodeId};
+
+/// What the view should do after the editor has seen a key press
+pub(crate) enum EditOutcome {
+    /// The key changed the text, caret or selection, or was part of an input method composition
+    Handled,
+    /// The user asked to stop editing
+    Finished,
+    /// The editor has no use for this key
+    Ignored,
+}
+
+/// In-place editing state for the text of a single node.
+/// Offsets are byte indices into `text` and are always kept on char boundaries.
+pub(crate) struct TextEdit {
+    pub(crate) node_id: NodeId,
+    /// The node as it was before editing started, so the whole edit can be undone in one step
+    pub(crate) original: Node,
+    pub(crate) text: String,
+    caret: usize,
+    /// Other end of the selection - equal to `caret` when nothing is selected
+    anchor: usize,
+}
+
+impl TextEdit {
+    pub(crate) fn new(original: Node) -> Self {
+        let text = original.text.clone();
+        let end = text.len();
+        TextEdit { node_id: original.id, original, text, caret: end, anchor: end }
+    }
+
+    pub(crate) fn caret(&self) -> usize {
+        self.caret
+    }
+
+    pub(crate) fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub(crate) fn set_caret(&mut self, offset: usize, extend_selection: bool) {
+        self.caret = offset.min(self.text.len());
+        if !extend_selection {
+            self.anchor = self.caret;
+        }
+    }
+
+    pub(crate) fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+    }
+
+    /// Replaces the current selection with `s`, leaving the caret after the inserted text.
+    /// Control characters other than newlines, like tabs and carriage returns, are left out of node labels.
+    pub(crate) fn insert(&mut self, s: &str) {
+        let s: String = s.chars().filter(|c| *c == '\n' || !c.is_control()).collect();
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), &s);
+        self.set_caret(selection.start + s.len(), false);
+    }
+
+    pub(crate) fn key_down(&mut self, ke: &KeyEvent) -> EditOutcome {
+        if is_composition_key(ke) {
+            return EditOutcome::Handled;
+        }
+        let extend = ke.mods.shift();
+        if HotKey::new(SysMods::Cmd, "a").matches(ke) {
+            self.select_all();
+        } else if HotKey::new(SysMods::Cmd, "c").matches(ke) {
+            self.copy_selection();
+        } else if HotKey::new(SysMods::Cmd, "x").matches(ke) {
+            self.copy_selection();
+            self.insert("");
+        } else if HotKey::new(SysMods::Cmd, "v").matches(ke) {
+            if let Some(pasted) = Application::global().clipboard().get_string() {
+                self.insert(&pasted);
+            }
+        } else if ke.mods.ctrl() || ke.mods.meta() {
+            return EditOutcome::Ignored;
+        } else {
+            match &ke.key {
+                KbKey::Escape => return EditOutcome::Finished,
+                KbKey::Enter => self.insert("\n"),
+                KbKey::Backspace => {
+                    if self.selection().is_empty() {
+                        self.anchor = self.prev_boundary(self.caret);
+                    }
+                    self.insert("");
+                }
+                KbKey::Delete => {
+                    if self.selection().is_empty() {
+                        self.anchor = self.next_boundary(self.caret);
+                    }
+                    self.insert("");
+                }
+                KbKey::ArrowLeft => {
+                    let selection = self.selection();
+                    if !extend && !selection.is_empty() {
+                        self.set_caret(selection.start, false);
+                    } else {
+                        self.set_caret(self.prev_boundary(self.caret), extend);
+                    }
+                }
+                KbKey::ArrowRight => {
+                    let selection = self.selection();
+                    if !extend && !selection.is_empty() {
+                        self.set_caret(selection.end, false);
+                    } else {
+                        self.set_caret(self.next_boundary(self.caret), extend);
+                    }
+                }
+                KbKey::ArrowUp => self.move_line(-1, extend),
+                KbKey::ArrowDown => self.move_line(1, extend),
+                KbKey::Home => self.set_caret(self.line_start(self.caret), extend),
+                KbKey::End => self.set_caret(self.line_end(self.caret), extend),
+                // Text from dead keys and input methods arrives already composed
+                KbKey::Character(s) => self.insert(s),
+                _ => return EditOutcome::Ignored,
+            }
+        }
+        EditOutcome::Handled
+    }
+
+    fn copy_selection(&self) {
+        let selection = self.selection();
+        if !selection.is_empty() {
+            Application::global().clipboard().put_string(&self.text[selection]);
+        }
+    }
+
+    fn prev_boundary(&self, offset: usize) -> usize {
+        self.text[..offset].char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, offset: usize) -> usize {
+        self.text[offset..].chars().next().map_or(offset, |c| offset + c.len_utf8())
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..].find('\n').map_or(self.text.len(), |i| offset + i)
+    }
+
+    /// Moves the caret `direction` lines up or down, keeping the same column in chars where possible
+    fn move_line(&mut self, direction: i32, extend: bool) {
+        let line_start = self.line_start(self.caret);
+        let column = self.text[line_start..self.caret].chars().count();
+        let target_line_start = if direction < 0 {
+            if line_start == 0 {
+                return self.set_caret(0, extend);
+            }
+            self.line_start(line_start - 1)
+        } else {
+            let line_end = self.line_end(self.caret);
+            if line_end == self.text.len() {
+                return self.set_caret(self.text.len(), extend);
+            }
+            line_end + 1
+        };
+        let target_line = &self.text[target_line_start..self.line_end(target_line_start)];
+        let offset_in_line = target_line.char_indices().nth(column).map_or(target_line.len(), |(i, _)| i);
+        self.set_caret(target_line_start + offset_in_line, extend);
+    }
+}
+
+/// Input methods reach the editor through the text they commit, which arrives as an ordinary key press
+/// carrying the whole composed string. Keys pressed while a composition is still open belong to the
+/// input method, so they're swallowed rather than typed or passed on to the view's shortcuts.
+fn is_composition_key(ke: &KeyEvent) -> bool {
+    ke.is_composing || ke.key == KbKey::Process
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Modifiers, Point};
+
+    use super::*;
+
+    #[test]
+    fn control_characters_are_not_inserted() {
+        let mut edit = TextEdit::new(Node::new(Point::ZERO, None));
+        edit.insert("a\tb\r\nc");
+        assert_eq!(edit.text, "ab\nc");
+        assert_eq!(edit.caret(), edit.text.len());
+        edit.set_caret(1, false);
+        edit.insert("\u{1b}é");
+        assert_eq!(edit.text, "aéb\nc");
+        assert_eq!(edit.caret(), 1 + 'é'.len_utf8());
+    }
+
+    #[test]
+    fn only_committed_input_method_text_is_inserted() {
+        let mut composing = KeyEvent::for_test(Modifiers::empty(), "n");
+        composing.is_composing = true;
+        assert!(is_composition_key(&composing));
+        assert!(is_composition_key(&KeyEvent::for_test(Modifiers::empty(), KbKey::Process)));
+        let committed = KeyEvent::for_test(Modifiers::empty(), "日本");
+        assert!(!is_composition_key(&committed));
+        let mut edit = TextEdit::new(Node::new(Point::ZERO, None));
+        if let KbKey::Character(s) = &committed.key {
+            edit.insert(s);
+        }
+        assert_eq!(edit.text, "日本");
+        assert_eq!(edit.caret(), edit.text.len());
+    }
+}
@@ -1,4 +1,4 @@
-use druid::{Point, Rect, Vec2};
+use druid::{Point, Rect, Size, Vec2};
 use druid::kurbo::Line;
 
 pub struct Viewport {
@@ -40,6 +40,11 @@ impl Viewport {
         ((point.to_vec2() / self.scale) + self.origin.to_vec2()).to_point()
     }
 
+    /// The part of the scene shown in a widget of the given size
+    pub fn visible_scene_rect(&self, screen_size: Size) -> Rect {
+        Rect::from_points(self.screen_coord_to_scene(Point::ORIGIN), self.screen_coord_to_scene(screen_size.to_vec2().to_point()))
+    }
+
     pub fn scene_rect_to_screen(&self, rect: Rect) -> Rect {
         Rect::from((self.scene_coord_to_screen(rect.origin()), self.scene_coord_to_screen(Point::new(rect.x1, rect.y1))))
     }
@@ -47,9 +52,5 @@ impl Viewport {
     pub fn scene_line_to_screen(&self, line: Line) -> Line {
         Line::new(self.scene_coord_to_screen(line.p0), self.scene_coord_to_screen(line.p1))
     }
-
-    pub fn screen_line_to_scene(&self, line: Line) -> Line {
-        Line::new(self.screen_coord_to_scene(line.p0), self.screen_coord_to_scene(line.p1))
-    }
 }
 
@@ -0,0 +1,34 @@
+//! A mind-map editor. It's started from `main.rs`, and is also a library so that the benchmarks in
+//! `benches/` can reach its internals through [`bench`].
+use druid::{AppLauncher, WidgetExt, WindowDesc};
+use druid::widget::EnvScope;
+
+use crate::config::ConfigFollower;
+use crate::graph_view::GraphView;
+use crate::theme::Theme;
+
+mod config;
+mod graph;
+mod graph_view;
+mod theme;
+
+pub use graph_view::bench;
+
+/// Opens the main window and runs the app until it's closed
+pub fn run() {
+    let config_path = config::path();
+    let (config, config_error) = match config_path.as_deref().map(|path| (path, config::load(path))) {
+        Some((path, Err(e))) => (None, Some(config::error_message(path, &e))),
+        Some((_, Ok(config))) => (config, None),
+        None => (None, None),
+    };
+    let theme = config.and_then(|config| config.theme).unwrap_or_default();
+    let main_window = WindowDesc::new(move || {
+        EnvScope::new(|env, theme: &Theme| theme.configure_env(env), GraphView::new())
+            .controller(ConfigFollower::new(config_path, config_error))
+    }).title("Tree");
+    // start the application. Here we pass in the application state, which is the theme
+    AppLauncher::with_window(main_window)
+        .launch(theme)
+        .expect("Failed to launch application");
+}
@@ -0,0 +1,183 @@
+use druid::{Color, Data, Env, Key};
+use serde::{Deserialize, Serialize};
+
+use crate::graph::style::ThemeColor;
+
+pub(crate) const BACKGROUND: Key<Color> = Key::new("tree-rs.theme.background");
+pub(crate) const GRID_DOT: Key<Color> = Key::new("tree-rs.theme.grid-dot");
+pub(crate) const ORIGIN_MARKER: Key<Color> = Key::new("tree-rs.theme.origin-marker");
+pub(crate) const NODE_FILL: Key<Color> = Key::new("tree-rs.theme.node-fill");
+pub(crate) const NODE_STROKE: Key<Color> = Key::new("tree-rs.theme.node-stroke");
+/// Node text and the text editing caret
+pub(crate) const NODE_TEXT: Key<Color> = Key::new("tree-rs.theme.node-text");
+pub(crate) const EDGE_STROKE: Key<Color> = Key::new("tree-rs.theme.edge-stroke");
+/// Selected elements, resize handles and the marquee
+pub(crate) const HIGHLIGHT: Key<Color> = Key::new("tree-rs.theme.highlight");
+pub(crate) const GUIDE: Key<Color> = Key::new("tree-rs.theme.guide");
+pub(crate) const PIN: Key<Color> = Key::new("tree-rs.theme.pin");
+
+/// Key holding a theme colour that styles can refer to
+pub(crate) fn theme_color_key(theme_color: ThemeColor) -> Key<Color> {
+    Key::new(match theme_color {
+        ThemeColor::Foreground => "tree-rs.theme.palette.foreground",
+        ThemeColor::Background => "tree-rs.theme.palette.background",
+        ThemeColor::Muted => "tree-rs.theme.palette.muted",
+        ThemeColor::Red => "tree-rs.theme.palette.red",
+        ThemeColor::Yellow => "tree-rs.theme.palette.yellow",
+        ThemeColor::Green => "tree-rs.theme.palette.green",
+        ThemeColor::Blue => "tree-rs.theme.palette.blue",
+        ThemeColor::MutedTint => "tree-rs.theme.palette.muted-tint",
+        ThemeColor::RedTint => "tree-rs.theme.palette.red-tint",
+        ThemeColor::YellowTint => "tree-rs.theme.palette.yellow-tint",
+        ThemeColor::GreenTint => "tree-rs.theme.palette.green-tint",
+        ThemeColor::BlueTint => "tree-rs.theme.palette.blue-tint",
+    })
+}
+
+/// Colours the graph is painted with, which are put into the `Env` under the keys above
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Theme {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl Data for Theme {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+struct ThemeColors {
+    background: Color,
+    grid_dot: Color,
+    origin_marker: Color,
+    node_fill: Color,
+    node_stroke: Color,
+    node_text: Color,
+    edge_stroke: Color,
+    highlight: Color,
+    guide: Color,
+    pin: Color,
+    /// In the order of `ThemeColor::ALL`
+    palette: [Color; 12],
+}
+
+const LIGHT: ThemeColors = ThemeColors {
+    background: Color::grey8(0xf0),
+    grid_dot: Color::grey8(0x61),
+    origin_marker: Color::rgb8(0x00, 0x00, 0xff),
+    node_fill: Color::WHITE,
+    node_stroke: Color::BLACK,
+    node_text: Color::BLACK,
+    edge_stroke: Color::BLACK,
+    highlight: Color::rgb8(0x75, 0xa7, 0xf8),
+    guide: Color::rgb8(0xf2, 0x4e, 0x9a),
+    pin: Color::rgb8(0xd0, 0x3a, 0x3a),
+    palette: [
+        Color::BLACK, Color::WHITE, Color::grey8(0x75),
+        Color::rgb8(0xd3, 0x2f, 0x2f), Color::rgb8(0xf9, 0xa8, 0x25), Color::rgb8(0x38, 0x8e, 0x3c), Color::rgb8(0x19, 0x76, 0xd2),
+        Color::grey8(0xe0), Color::rgb8(0xf8, 0xbb, 0xd0), Color::rgb8(0xff, 0xf3, 0xb0), Color::rgb8(0xc8, 0xe6, 0xc9), Color::rgb8(0xbb, 0xde, 0xfb),
+    ],
+};
+
+const DARK: ThemeColors = ThemeColors {
+    background: Color::grey8(0x1e),
+    grid_dot: Color::grey8(0x5a),
+    origin_marker: Color::rgb8(0x64, 0xa0, 0xff),
+    node_fill: Color::grey8(0x2b),
+    node_stroke: Color::grey8(0xd0),
+    node_text: Color::grey8(0xee),
+    edge_stroke: Color::grey8(0xc0),
+    highlight: Color::rgb8(0x4c, 0x8d, 0xff),
+    guide: Color::rgb8(0xff, 0x5f, 0xa8),
+    pin: Color::rgb8(0xff, 0x6b, 0x6b),
+    palette: [
+        Color::grey8(0xee), Color::grey8(0x2b), Color::grey8(0x9e),
+        Color::rgb8(0xef, 0x53, 0x50), Color::rgb8(0xfd, 0xd8, 0x35), Color::rgb8(0x66, 0xbb, 0x6a), Color::rgb8(0x42, 0xa5, 0xf5),
+        Color::grey8(0x3a), Color::rgb8(0x5c, 0x2b, 0x33), Color::rgb8(0x5a, 0x52, 0x26), Color::rgb8(0x2e, 0x4d, 0x32), Color::rgb8(0x1f, 0x3a, 0x56),
+    ],
+};
+
+const HIGH_CONTRAST: ThemeColors = ThemeColors {
+    background: Color::BLACK,
+    grid_dot: Color::grey8(0x80),
+    origin_marker: Color::rgb8(0x00, 0xff, 0xff),
+    node_fill: Color::BLACK,
+    node_stroke: Color::WHITE,
+    node_text: Color::WHITE,
+    edge_stroke: Color::WHITE,
+    highlight: Color::rgb8(0xff, 0xff, 0x00),
+    guide: Color::rgb8(0xff, 0x00, 0xff),
+    pin: Color::rgb8(0xff, 0x40, 0x40),
+    palette: [
+        Color::WHITE, Color::BLACK, Color::grey8(0xc0),
+        Color::rgb8(0xff, 0x40, 0x40), Color::rgb8(0xff, 0xff, 0x00), Color::rgb8(0x00, 0xff, 0x00), Color::rgb8(0x40, 0xa0, 0xff),
+        Color::grey8(0x30), Color::rgb8(0x60, 0x00, 0x00), Color::rgb8(0x50, 0x50, 0x00), Color::rgb8(0x00, 0x4d, 0x00), Color::rgb8(0x00, 0x2b, 0x66),
+    ],
+};
+
+impl Theme {
+    /// The theme after this one when cycling through themes in the UI
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::HighContrast,
+            Theme::HighContrast => Theme::Light,
+        }
+    }
+
+    fn colors(self) -> &'static ThemeColors {
+        match self {
+            Theme::Light => &LIGHT,
+            Theme::Dark => &DARK,
+            Theme::HighContrast => &HIGH_CONTRAST,
+        }
+    }
+
+    /// Every key the theme sets, with its colour
+    fn entries(self) -> Vec<(Key<Color>, Color)> {
+        let colors = self.colors();
+        let mut entries = vec![
+            (BACKGROUND, colors.background.clone()),
+            (GRID_DOT, colors.grid_dot.clone()),
+            (ORIGIN_MARKER, colors.origin_marker.clone()),
+            (NODE_FILL, colors.node_fill.clone()),
+            (NODE_STROKE, colors.node_stroke.clone()),
+            (NODE_TEXT, colors.node_text.clone()),
+            (EDGE_STROKE, colors.edge_stroke.clone()),
+            (HIGHLIGHT, colors.highlight.clone()),
+            (GUIDE, colors.guide.clone()),
+            (PIN, colors.pin.clone()),
+        ];
+        entries.extend(ThemeColor::ALL.into_iter().map(theme_color_key).zip(colors.palette.iter().cloned()));
+        entries
+    }
+
+    pub(crate) fn configure_env(self, env: &mut Env) {
+        for (key, color) in self.entries() {
+            env.set(key, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_theme_sets_every_color() {
+        for theme in [Theme::Light, Theme::Dark, Theme::HighContrast] {
+            let entries = theme.entries();
+            let keys = [BACKGROUND, GRID_DOT, ORIGIN_MARKER, NODE_FILL, NODE_STROKE, NODE_TEXT, EDGE_STROKE, HIGHLIGHT, GUIDE, PIN]
+                .into_iter().chain(ThemeColor::ALL.into_iter().map(theme_color_key));
+            for key in keys {
+                assert_eq!(entries.iter().filter(|(k, _)| *k == key).count(), 1, "{:?} doesn't set {:?} once", theme, key);
+            }
+            let background = entries.iter().find(|(key, _)| *key == theme_color_key(ThemeColor::Background)).unwrap();
+            assert_eq!(background.1, theme.colors().palette[1]);
+        }
+    }
+}